@@ -0,0 +1,354 @@
+//! Tuning of PID gains with the relay method of Åström and Hägglund
+//!
+//! In place of the controller, a relay drives the mechanism one way whenever it is short of its target and the other
+//! way whenever it is past it. The mechanism settles into an oscillation at its ultimate period, the period at which a
+//! proportional controller would sit on the edge of stability, and the size of the oscillation gives the ultimate
+//! gain, that controller's gain. A tuning rule turns the two into [`Gains`].
+//!
+//! The loops of a [`TankDrive`](crate::tank_drive::TankDrive) are cascaded, so the velocity loop is tuned first with a
+//! [`VelocityAutotune`], and the position loop is then tuned with a [`PositionAutotune`] running through it.
+
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+use libm::{fabs, sqrt};
+#[cfg(feature = "vex-rt")]
+use uom::si::f64::Ratio;
+use uom::{
+	si::{
+		angle::radian,
+		angular_velocity::radian_per_second,
+		electric_potential::volt,
+		f64::{Angle, AngularVelocity, ElectricPotential, Time},
+		time::second,
+	},
+	ConstZero,
+};
+#[cfg(feature = "vex-rt")]
+use vex_rt::{
+	rtos::{Context, Loop},
+	select,
+};
+
+use crate::{
+	clock::Clock,
+	pid::{PidGains, PidInput, PidOutput, Signal},
+	Gains,
+};
+#[cfg(feature = "vex-rt")]
+use crate::{clock::RtosClock, motor::SmartMotor, pid::VelocityController, PID_CYCLE_DURATION};
+
+/// Number of oscillations averaged over unless another number is given
+const DEFAULT_PERIODS: usize = 4;
+
+/// Number of oscillations ignored at the start, while the mechanism settles into its cycle
+const DISCARDED_PERIODS: usize = 1;
+
+/// Rule for turning the ultimate gain and period into PID gains
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TuningRule {
+	/// Classic Ziegler-Nichols gains, which respond quickly but overshoot by a fair amount
+	ZieglerNichols,
+	/// Tyreus-Luyben gains, which are more conservative, trading speed for less overshoot and more robustness
+	TyreusLuyben,
+}
+
+/// Gain and period at which a mechanism under proportional control oscillates steadily, for a loop measuring `I` and
+/// outputting `O`
+#[derive(Clone, Copy, Debug)]
+pub struct UltimatePoint<I: PidInput, O: PidOutput<I>> {
+	/// Proportional gain at which the loop oscillates
+	pub gain: O::Proportional,
+	/// Period of the oscillation
+	pub period: Time,
+}
+
+impl<I: PidInput, O: PidOutput<I>> UltimatePoint<I, O> {
+	/// Gains given by a tuning rule for the loop the ultimate point was measured on
+	pub fn gains(&self, rule: TuningRule) -> PidGains<I, O> {
+		let gain: f64 = self.gain.to_base();
+
+		let (proportional, integral_time, derivative_time): (f64, Time, Time) = match rule {
+			TuningRule::ZieglerNichols => (gain * 0.6, self.period / 2.0, self.period / 8.0),
+			TuningRule::TyreusLuyben => (gain / 2.2, self.period * 2.2, self.period / 6.3),
+		};
+
+		Gains {
+			proportional: Signal::from_base(proportional),
+			integral: Signal::from_base(proportional / integral_time.get::<second>()),
+			derivative: Signal::from_base(proportional * derivative_time.get::<second>()),
+		}
+	}
+}
+
+/// Relay and the measurement of the oscillation it causes, in SI base units
+struct Relay<C> {
+	amplitude: f64,
+	bias: f64,
+	hysteresis: f64,
+	periods: usize,
+
+	high: Option<bool>,
+	last_switch: Option<Time>,
+	highest_error: f64,
+	lowest_error: f64,
+	oscillations: Vec<(f64, Time)>,
+
+	clock: C,
+}
+
+impl<C: Clock> Relay<C> {
+	fn new(amplitude: f64, clock: C) -> Self {
+		Self {
+			amplitude: fabs(amplitude),
+			bias: 0.0,
+			hysteresis: 0.0,
+			periods: DEFAULT_PERIODS,
+
+			high: None,
+			last_switch: None,
+			highest_error: f64::NEG_INFINITY,
+			lowest_error: f64::INFINITY,
+			oscillations: Vec::new(),
+
+			clock,
+		}
+	}
+
+	fn is_complete(&self) -> bool { self.oscillations.len() >= DISCARDED_PERIODS + self.periods }
+
+	fn cycle(&mut self, error: f64) -> f64 {
+		if self.is_complete() {
+			return self.bias;
+		}
+
+		// The hysteresis keeps noise around the target from flicking the relay back and forth
+		let high: bool = match self.high {
+			None => error > 0.0,
+			Some(true) => error > -self.hysteresis,
+			Some(false) => error > self.hysteresis,
+		};
+
+		if high && self.high == Some(false) {
+			let now: Time = self.clock.now();
+
+			if let Some(last_switch) = self.last_switch {
+				self.oscillations
+					.push(((self.highest_error - self.lowest_error) / 2.0, now - last_switch));
+			}
+
+			self.last_switch = Some(now);
+			self.highest_error = f64::NEG_INFINITY;
+			self.lowest_error = f64::INFINITY;
+		}
+
+		self.high = Some(high);
+		self.highest_error = self.highest_error.max(error);
+		self.lowest_error = self.lowest_error.min(error);
+
+		if self.is_complete() {
+			self.bias
+		} else if high {
+			self.bias + self.amplitude
+		} else {
+			self.bias - self.amplitude
+		}
+	}
+
+	/// Gets the ultimate point measured, for a loop whose input and output the relay has been working with in SI base
+	/// units
+	fn result<I: PidInput, O: PidOutput<I>>(&self) -> Option<UltimatePoint<I, O>> {
+		if !self.is_complete() {
+			return None;
+		}
+
+		let measured: &[(f64, Time)] = &self.oscillations[self.oscillations.len() - self.periods..];
+
+		let amplitude: f64 = measured.iter().map(|(amplitude, _)| amplitude).sum::<f64>() / self.periods as f64;
+		let period: Time = measured.iter().fold(Time::ZERO, |sum, (_, period)| sum + *period) / self.periods as f64;
+
+		// Describing function of a relay with hysteresis, the first harmonic of its square wave output
+		let excess: f64 = amplitude * amplitude - self.hysteresis * self.hysteresis;
+
+		if excess <= 0.0 {
+			return None;
+		}
+
+		Some(UltimatePoint {
+			gain: Signal::from_base(4.0 * self.amplitude / (PI * sqrt(excess))),
+			period,
+		})
+	}
+}
+
+/// Relay autotune of a position loop, whose output is the velocity asked of the velocity loop beneath it
+pub struct PositionAutotune<C> {
+	relay: Relay<C>,
+	target: Angle,
+}
+
+#[cfg(feature = "vex-rt")]
+impl PositionAutotune<RtosClock> {
+	/// Creates an autotune which oscillates around a target by asking for a velocity either way, measuring time using
+	/// the RTOS clock
+	pub fn new(target: Angle, velocity: AngularVelocity) -> Self { Self::with_clock(target, velocity, RtosClock) }
+
+	/// Oscillates a set of motors geared together until the autotune completes, a timeout passes or the context is
+	/// cancelled, with their velocity held by a controller with already tuned gains
+	///
+	/// The motors are stopped afterwards, and the ultimate point is returned if the autotune completed.
+	pub fn run<M: SmartMotor, const N: usize>(
+		&mut self, motors: &mut [M; N], velocity_gains: PidGains<AngularVelocity, ElectricPotential>, timeout: Time,
+		ctx: &Context,
+	) -> Result<Option<UltimatePoint<Angle, AngularVelocity>>, M::Error> {
+		let mut velocity_controller =
+			VelocityController::new(AngularVelocity::ZERO, velocity_gains, AngularVelocity::ZERO);
+
+		let start: Time = self.relay.clock.now();
+		let mut pause = Loop::new(PID_CYCLE_DURATION);
+
+		while !self.is_complete() && self.relay.clock.now() - start < timeout {
+			velocity_controller.set_target(self.cycle(motors[0].get_position()?));
+			let voltage: ElectricPotential = velocity_controller.cycle(motors[0].get_actual_velocity()?);
+
+			for motor in motors.iter_mut() {
+				motor.move_voltage(voltage)?;
+			}
+
+			select! {
+				_ = ctx.done() => break,
+				_ = pause.select() => continue
+			}
+		}
+
+		stop(motors)?;
+		Ok(self.result())
+	}
+}
+
+impl<C: Clock> PositionAutotune<C> {
+	/// Creates an autotune which oscillates around a target by asking for a velocity either way, measuring time using
+	/// the given clock
+	pub fn with_clock(target: Angle, velocity: AngularVelocity, clock: C) -> Self {
+		Self {
+			relay: Relay::new(velocity.get::<radian_per_second>(), clock),
+			target,
+		}
+	}
+
+	/// Sets how far past the target the mechanism has to go before the relay switches, which should be a little more
+	/// than the noise in the position
+	pub fn with_hysteresis(mut self, hysteresis: Angle) -> Self {
+		self.relay.hysteresis = fabs(hysteresis.get::<radian>());
+		self
+	}
+
+	/// Sets the number of oscillations averaged over, which defaults to 4
+	pub fn with_periods(mut self, periods: usize) -> Self {
+		self.relay.periods = periods.max(1);
+		self
+	}
+
+	/// Whether enough oscillations have been measured, after which the output stays at zero
+	pub fn is_complete(&self) -> bool { self.relay.is_complete() }
+
+	/// Runs a cycle of the relay and returns the velocity the mechanism should be moving at
+	pub fn cycle(&mut self, current: Angle) -> AngularVelocity {
+		AngularVelocity::new::<radian_per_second>(self.relay.cycle((self.target - current).get::<radian>()))
+	}
+
+	/// Gets the ultimate point measured, once the autotune is complete
+	pub fn result(&self) -> Option<UltimatePoint<Angle, AngularVelocity>> { self.relay.result() }
+}
+
+/// Relay autotune of a velocity loop, whose output is the voltage applied to the motors
+pub struct VelocityAutotune<C> {
+	relay: Relay<C>,
+	target: AngularVelocity,
+}
+
+#[cfg(feature = "vex-rt")]
+impl VelocityAutotune<RtosClock> {
+	/// Creates an autotune which oscillates around a target by applying a voltage either way, measuring time using
+	/// the RTOS clock
+	pub fn new(target: AngularVelocity, voltage: ElectricPotential) -> Self {
+		Self::with_clock(target, voltage, RtosClock)
+	}
+
+	/// Oscillates a set of motors geared together until the autotune completes, a timeout passes or the context is
+	/// cancelled
+	///
+	/// The motors are stopped afterwards, and the ultimate point is returned if the autotune completed.
+	pub fn run<M: SmartMotor, const N: usize>(
+		&mut self, motors: &mut [M; N], timeout: Time, ctx: &Context,
+	) -> Result<Option<UltimatePoint<AngularVelocity, ElectricPotential>>, M::Error> {
+		let start: Time = self.relay.clock.now();
+		let mut pause = Loop::new(PID_CYCLE_DURATION);
+
+		while !self.is_complete() && self.relay.clock.now() - start < timeout {
+			let voltage: ElectricPotential = self.cycle(motors[0].get_actual_velocity()?);
+
+			for motor in motors.iter_mut() {
+				motor.move_voltage(voltage)?;
+			}
+
+			select! {
+				_ = ctx.done() => break,
+				_ = pause.select() => continue
+			}
+		}
+
+		stop(motors)?;
+		Ok(self.result())
+	}
+}
+
+impl<C: Clock> VelocityAutotune<C> {
+	/// Creates an autotune which oscillates around a target by applying a voltage either way, measuring time using the
+	/// given clock
+	pub fn with_clock(target: AngularVelocity, voltage: ElectricPotential, clock: C) -> Self {
+		Self {
+			relay: Relay::new(voltage.get::<volt>(), clock),
+			target,
+		}
+	}
+
+	/// Sets the voltage the relay switches around, which should be roughly the voltage that holds the target velocity
+	/// so that the oscillation is even
+	pub fn with_bias(mut self, bias: ElectricPotential) -> Self {
+		self.relay.bias = bias.get::<volt>();
+		self
+	}
+
+	/// Sets how far past the target the mechanism has to go before the relay switches, which should be a little more
+	/// than the noise in the velocity
+	pub fn with_hysteresis(mut self, hysteresis: AngularVelocity) -> Self {
+		self.relay.hysteresis = fabs(hysteresis.get::<radian_per_second>());
+		self
+	}
+
+	/// Sets the number of oscillations averaged over, which defaults to 4
+	pub fn with_periods(mut self, periods: usize) -> Self {
+		self.relay.periods = periods.max(1);
+		self
+	}
+
+	/// Whether enough oscillations have been measured, after which the output stays at the bias
+	pub fn is_complete(&self) -> bool { self.relay.is_complete() }
+
+	/// Runs a cycle of the relay and returns the voltage to apply to the motors
+	pub fn cycle(&mut self, current: AngularVelocity) -> ElectricPotential {
+		ElectricPotential::new::<volt>(self.relay.cycle((self.target - current).get::<radian_per_second>()))
+	}
+
+	/// Gets the ultimate point measured, once the autotune is complete
+	pub fn result(&self) -> Option<UltimatePoint<AngularVelocity, ElectricPotential>> { self.relay.result() }
+}
+
+#[cfg(feature = "vex-rt")]
+fn stop<M: SmartMotor>(motors: &mut [M]) -> Result<(), M::Error> {
+	for motor in motors.iter_mut() {
+		motor.move_ratio(Ratio::ZERO)?;
+	}
+	Ok(())
+}
@@ -0,0 +1,63 @@
+//! Sources of time for components which integrate or differentiate over time, so that they can be driven by a fake
+//! clock off the robot
+
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+use uom::si::f64::Time;
+
+/// Monotonic source of time
+pub trait Clock {
+	/// Gets the time elapsed since a fixed starting point
+	fn now(&self) -> Time;
+}
+
+/// Clock backed by the RTOS tick counter
+#[cfg(feature = "vex-rt")]
+#[derive(Clone, Copy, Default)]
+pub struct RtosClock;
+
+#[cfg(feature = "vex-rt")]
+impl Clock for RtosClock {
+	fn now(&self) -> Time {
+		Time::new::<uom::si::time::microsecond>(vex_rt::rtos::time_since_start().as_micros() as f64)
+	}
+}
+
+/// Clock used by components which are not given one
+///
+/// On the robot this is the RTOS clock. Off the robot there is no clock to read, so it is a [`ManualClock`] which
+/// stays at zero unless a handle to it is advanced.
+#[cfg(feature = "vex-rt")]
+pub type DefaultClock = RtosClock;
+
+/// Clock used by components which are not given one
+///
+/// On the robot this is the RTOS clock. Off the robot there is no clock to read, so it is a [`ManualClock`] which
+/// stays at zero unless a handle to it is advanced.
+#[cfg(not(feature = "vex-rt"))]
+pub type DefaultClock = ManualClock;
+
+/// Clock which only moves when it is told to
+///
+/// Clones share the same time, so a test or simulation can keep a clone to advance time while a controller owns the
+/// other.
+#[derive(Clone, Default)]
+pub struct ManualClock {
+	now: Rc<Cell<Time>>,
+}
+
+impl ManualClock {
+	/// Creates a clock starting at zero
+	pub fn new() -> Self { Self::default() }
+
+	/// Moves the clock forward by a given amount of time
+	pub fn advance(&self, time: Time) { self.now.set(self.now.get() + time); }
+
+	/// Sets the current time of the clock
+	pub fn set(&self, now: Time) { self.now.set(now); }
+}
+
+impl Clock for ManualClock {
+	fn now(&self) -> Time { self.now.get() }
+}
@@ -0,0 +1,143 @@
+//! Abstraction over the rotation sensors used by tracking wheels, with simulated encoders for running odometry off
+//! the robot
+
+use alloc::{rc::Rc, vec::Vec};
+use core::{cell::Cell, convert::Infallible};
+
+use uom::{si::f64::Angle, ConstZero};
+
+use crate::motor::SmartMotor;
+
+/// Sensor which measures the absolute rotation of a shaft
+pub trait Encoder {
+	/// Error returned when the sensor cannot be read or written
+	type Error;
+
+	/// Gets the current rotation of the shaft
+	fn get_position(&self) -> Result<Angle, Self::Error>;
+
+	/// Sets the current rotation of the shaft to a given value
+	fn set_position(&mut self, position: Angle) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "vex-rt")]
+impl Encoder for vex_rt::rotation::RotationSensor {
+	type Error = vex_rt::rotation::RotationSensorError;
+
+	fn get_position(&self) -> Result<Angle, Self::Error> { vex_rt::rotation::RotationSensor::get_position(self) }
+
+	fn set_position(&mut self, position: Angle) -> Result<(), Self::Error> {
+		vex_rt::rotation::RotationSensor::set_position(self, position)
+	}
+}
+
+/// Encoder backed by the integrated encoder of a smart motor, for odometry which tracks the drive wheels
+///
+/// The motor's own zero position is left alone, positions set through [`Encoder::set_position`] are kept as an
+/// offset instead, so the adapter can be given a separate handle to a motor the drive train is also taring.
+pub struct MotorEncoder<M> {
+	motor: M,
+	offset: Angle,
+}
+
+impl<M> MotorEncoder<M> {
+	/// Reads positions from a motor
+	pub fn new(motor: M) -> Self {
+		Self {
+			motor,
+			offset: Angle::ZERO,
+		}
+	}
+}
+
+impl<M: SmartMotor> Encoder for MotorEncoder<M> {
+	type Error = M::Error;
+
+	fn get_position(&self) -> Result<Angle, Self::Error> { Ok(self.motor.get_position()? + self.offset) }
+
+	fn set_position(&mut self, position: Angle) -> Result<(), Self::Error> {
+		self.offset = position - self.motor.get_position()?;
+		Ok(())
+	}
+}
+
+/// In-memory encoder whose rotation is set directly
+///
+/// Clones share the same rotation, so a test or simulation can keep a clone to turn the shaft while an odometry
+/// system owns the other.
+#[derive(Clone, Default)]
+pub struct MockEncoder {
+	position: Rc<Cell<Angle>>,
+}
+
+impl MockEncoder {
+	/// Creates an encoder at zero rotation
+	pub fn new() -> Self { Self::default() }
+
+	/// Turns the shaft by a relative angle
+	pub fn rotate(&self, angle: Angle) { self.position.set(self.position.get() + angle); }
+}
+
+impl Encoder for MockEncoder {
+	type Error = Infallible;
+
+	fn get_position(&self) -> Result<Angle, Self::Error> { Ok(self.position.get()) }
+
+	fn set_position(&mut self, position: Angle) -> Result<(), Self::Error> {
+		self.position.set(position);
+		Ok(())
+	}
+}
+
+/// Encoder which plays back a recorded sequence of readings, such as logged match data
+///
+/// Every call to [`Encoder::get_position`] returns the next sample, and the last sample is repeated once the
+/// sequence has been exhausted.
+pub struct ScriptedEncoder {
+	samples: Vec<Angle>,
+	next_sample: Cell<usize>,
+	offset: Cell<Angle>,
+}
+
+impl ScriptedEncoder {
+	/// Creates an encoder which plays back the given readings in order
+	pub fn new(samples: Vec<Angle>) -> Self {
+		Self {
+			samples,
+			next_sample: Cell::new(0),
+			offset: Cell::new(Angle::ZERO),
+		}
+	}
+
+	/// Whether every sample has been read
+	pub fn is_finished(&self) -> bool { self.next_sample.get() >= self.samples.len() }
+
+	fn sample(&self, index: usize) -> Angle {
+		self.samples
+			.get(index.min(self.samples.len().saturating_sub(1)))
+			.copied()
+			.unwrap_or(Angle::ZERO)
+	}
+}
+
+impl FromIterator<Angle> for ScriptedEncoder {
+	fn from_iter<T: IntoIterator<Item = Angle>>(iter: T) -> Self { Self::new(iter.into_iter().collect()) }
+}
+
+impl Encoder for ScriptedEncoder {
+	type Error = Infallible;
+
+	fn get_position(&self) -> Result<Angle, Self::Error> {
+		let index: usize = self.next_sample.get();
+		self.next_sample.set(index + 1);
+
+		Ok(self.sample(index) + self.offset.get())
+	}
+
+	fn set_position(&mut self, position: Angle) -> Result<(), Self::Error> {
+		// Offset relative to the most recently read sample so that playback continues from the new position
+		let current: Angle = self.sample(self.next_sample.get().saturating_sub(1));
+		self.offset.set(position - current);
+		Ok(())
+	}
+}
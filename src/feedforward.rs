@@ -0,0 +1,222 @@
+//! Feedforward models of motors, which give the voltage needed to move at a velocity and acceleration so that
+//! feedback only has to correct whatever the model gets wrong
+
+use alloc::vec::Vec;
+use core::ops::Div;
+
+use libm::{copysign, fabs};
+#[cfg(feature = "vex-rt")]
+use uom::si::time::millisecond;
+use uom::{
+	si::{
+		angular_acceleration::radian_per_second_squared,
+		angular_velocity::radian_per_second,
+		electric_potential::volt,
+		f64::{AngularAcceleration, AngularVelocity, ElectricPotential, Time},
+	},
+	ConstZero,
+};
+#[cfg(feature = "vex-rt")]
+use vex_rt::{
+	rtos::{Context, Loop},
+	select,
+};
+
+use crate::math::least_squares;
+#[cfg(feature = "vex-rt")]
+use crate::{
+	clock::{Clock, RtosClock},
+	motor::SmartMotor,
+	PID_CYCLE_DURATION,
+};
+
+/// Voltage needed per unit of velocity
+pub type VelocityGain = <ElectricPotential as Div<AngularVelocity>>::Output;
+
+/// Voltage needed per unit of acceleration
+pub type AccelerationGain = <ElectricPotential as Div<AngularAcceleration>>::Output;
+
+/// Slowest a motor can move and be taken to be moving, below which static friction holds it in a way the model does
+/// not describe
+const MOVING_RADIANS_PER_SECOND: f64 = 0.1;
+
+/// Most times longer the time between two samples can be than the time between the samples next to them before the
+/// log is taken to have a gap there, such as where recording paused between phases of a run
+const MAX_SAMPLE_GAP_RATIO: f64 = 2.0;
+
+/// Time the motors are left to come to rest between the ramp and the step of a characterisation run
+#[cfg(feature = "vex-rt")]
+const REST_MILLISECONDS: f64 = 1000.0;
+
+/// Model of the voltage a motor needs, as the sum of a constant voltage against friction, a voltage proportional to
+/// velocity against back EMF and a voltage proportional to acceleration against inertia
+#[derive(Clone, Copy, Debug)]
+pub struct MotorFeedforward {
+	/// Voltage needed to overcome static friction, kS
+	pub static_voltage: ElectricPotential,
+	/// Voltage needed per unit of velocity, kV
+	pub velocity_gain: VelocityGain,
+	/// Voltage needed per unit of acceleration, kA
+	pub acceleration_gain: AccelerationGain,
+}
+
+impl MotorFeedforward {
+	/// Creates a model from its constants
+	pub fn new(
+		static_voltage: ElectricPotential, velocity_gain: VelocityGain, acceleration_gain: AccelerationGain,
+	) -> Self {
+		Self {
+			static_voltage,
+			velocity_gain,
+			acceleration_gain,
+		}
+	}
+
+	/// Gets the voltage which moves the motor at a velocity while accelerating at a rate
+	pub fn calculate(&self, velocity: AngularVelocity, acceleration: AngularAcceleration) -> ElectricPotential {
+		let friction: ElectricPotential = if velocity > AngularVelocity::ZERO {
+			self.static_voltage
+		} else if velocity < AngularVelocity::ZERO {
+			-self.static_voltage
+		} else {
+			ElectricPotential::ZERO
+		};
+
+		let back_emf: ElectricPotential = self.velocity_gain * velocity;
+		let inertia: ElectricPotential = self.acceleration_gain * acceleration;
+
+		friction + back_emf + inertia
+	}
+}
+
+/// Log of the voltage applied to a motor and the velocity it moved at, from which a [`MotorFeedforward`] is fitted
+///
+/// The log needs both slow and sudden changes in voltage for every constant to be told apart, such as a slow ramp,
+/// where acceleration is negligible and the voltage is spent on friction and velocity, and a sudden step, where most
+/// of it is spent on acceleration.
+#[derive(Clone, Default)]
+pub struct Characterisation {
+	samples: Vec<(Time, ElectricPotential, AngularVelocity)>,
+}
+
+impl Characterisation {
+	/// Creates an empty log
+	pub fn new() -> Self { Self::default() }
+
+	/// Adds the voltage applied and the velocity measured at a point in time
+	pub fn record(&mut self, time: Time, voltage: ElectricPotential, velocity: AngularVelocity) {
+		self.samples.push((time, voltage, velocity));
+	}
+
+	/// Fits the model to the log by least squares, if the log has enough variety in it to tell every constant apart
+	///
+	/// The acceleration at each sample is taken from the velocities either side of it. Samples where the motor is
+	/// standing still are left out, as are samples next to a gap in the log, such as the rest between the ramp and the
+	/// step of a characterisation run, whose acceleration would be measured across the gap.
+	pub fn fit(&self) -> Option<MotorFeedforward> {
+		let rows = self.samples.windows(3).filter_map(|window| {
+			let (before_time, _, before_velocity) = window[0];
+			let (time, voltage, velocity) = window[1];
+			let (after_time, _, after_velocity) = window[2];
+
+			let velocity: f64 = velocity.get::<radian_per_second>();
+
+			let time_before: Time = time - before_time;
+			let time_after: Time = after_time - time;
+
+			let gap: bool =
+				time_before > time_after * MAX_SAMPLE_GAP_RATIO || time_after > time_before * MAX_SAMPLE_GAP_RATIO;
+
+			if after_time <= before_time || gap || fabs(velocity) < MOVING_RADIANS_PER_SECOND {
+				return None;
+			}
+
+			let acceleration: AngularAcceleration =
+				((after_velocity - before_velocity) / (after_time - before_time)).into();
+
+			Some((
+				[
+					copysign(1.0, velocity),
+					velocity,
+					acceleration.get::<radian_per_second_squared>(),
+				],
+				voltage.get::<volt>(),
+			))
+		});
+
+		let [static_voltage, velocity_gain, acceleration_gain] = least_squares(rows)?;
+
+		let volt_unit: ElectricPotential = ElectricPotential::new::<volt>(1.0);
+
+		Some(MotorFeedforward {
+			static_voltage: volt_unit * static_voltage,
+			velocity_gain: volt_unit * velocity_gain / AngularVelocity::new::<radian_per_second>(1.0),
+			acceleration_gain: volt_unit * acceleration_gain
+				/ AngularAcceleration::new::<radian_per_second_squared>(1.0),
+		})
+	}
+}
+
+#[cfg(feature = "vex-rt")]
+impl Characterisation {
+	/// Logs a set of motors geared together while they ramp slowly forwards and then step suddenly backwards, so that
+	/// a drive train ends up close to where it started
+	///
+	/// The ramp rises to a voltage over a time, and the step holds the opposite of a voltage for a time. The motors
+	/// are stopped afterwards, and whatever was logged before the context was cancelled is returned.
+	pub fn run<M: SmartMotor, const N: usize>(
+		motors: &mut [M; N], ramp_voltage: ElectricPotential, ramp_time: Time, step_voltage: ElectricPotential,
+		step_time: Time, ctx: &Context,
+	) -> Result<Self, M::Error> {
+		let mut log: Self = Self::new();
+
+		let rest_time: Time = Time::new::<millisecond>(REST_MILLISECONDS);
+
+		let ramp = |elapsed: Time| ramp_voltage * (elapsed / ramp_time);
+
+		if log.run_phase(motors, ramp_time, ramp, true, ctx)?
+			&& log.run_phase(motors, rest_time, |_| ElectricPotential::ZERO, false, ctx)?
+		{
+			log.run_phase(motors, step_time, |_| -step_voltage, true, ctx)?;
+		}
+
+		for motor in motors.iter_mut() {
+			motor.move_voltage(ElectricPotential::ZERO)?;
+		}
+
+		Ok(log)
+	}
+
+	/// Drives the motors with a voltage which changes over time, returning whether the phase ran to the end rather
+	/// than being cancelled
+	fn run_phase<M: SmartMotor, const N: usize>(
+		&mut self, motors: &mut [M; N], duration: Time, voltage: impl Fn(Time) -> ElectricPotential, record: bool,
+		ctx: &Context,
+	) -> Result<bool, M::Error> {
+		let start: Time = RtosClock.now();
+		let mut pause = Loop::new(PID_CYCLE_DURATION);
+
+		loop {
+			let now: Time = RtosClock.now();
+
+			if now - start >= duration {
+				return Ok(true);
+			}
+
+			let voltage: ElectricPotential = voltage(now - start);
+
+			for motor in motors.iter_mut() {
+				motor.move_voltage(voltage)?;
+			}
+
+			if record {
+				self.record(now, voltage, motors[0].get_actual_velocity()?);
+			}
+
+			select! {
+				_ = ctx.done() => return Ok(false),
+				_ = pause.select() => continue
+			}
+		}
+	}
+}
@@ -0,0 +1,225 @@
+//! Dimensions of the competition field and the positions robots start from, in field coordinates
+//!
+//! Field coordinates put the origin in the corner to the left of the red drivers, with x running along the red
+//! alliance wall to their right and y running away from them towards the blue alliance wall. Headings are measured
+//! clockwise from the y axis, so a robot facing the blue wall is at 0° and one facing the red wall is at 180°.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use libm::fabs;
+use uom::{
+	si::{
+		angle::degree,
+		f64::{Angle, Length, Ratio},
+		length::meter,
+	},
+	ConstZero,
+};
+
+use crate::{
+	coordinates::{Coordinates, Position},
+	math::RealAngle,
+};
+
+/// Length of each side of the field, 12 feet
+pub const FIELD_SIZE: Length = Length {
+	dimension: PhantomData,
+	units: PhantomData,
+	value: 3.6576,
+};
+
+/// Length of each side of a foam tile, 2 feet
+pub const TILE_SIZE: Length = Length {
+	dimension: PhantomData,
+	units: PhantomData,
+	value: 0.6096,
+};
+
+/// Number of tiles along each side of the field
+pub const TILES_PER_SIDE: u8 = 6;
+
+/// Gets the centre of a tile, counting columns along the x axis and rows along the y axis from zero
+pub fn tile_centre(column: u8, row: u8) -> Coordinates {
+	Coordinates::new(
+		TILE_SIZE * (f64::from(column) + 0.5),
+		TILE_SIZE * (f64::from(row) + 0.5),
+	)
+}
+
+/// Tile a robot starts a match on, named by alliance and by which side it is on from that alliance's drivers
+///
+/// Each starting tile is on the second tile in from the side walls, against the alliance's own wall, and the robot
+/// starts in the middle of it facing across the field. Routines which start elsewhere on the tile can adjust the
+/// [`position`](Self::position) before handing it to the odometry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StartingTile {
+	/// Left of the red drivers
+	RedLeft,
+	/// Right of the red drivers
+	RedRight,
+	/// Left of the blue drivers
+	BlueLeft,
+	/// Right of the blue drivers
+	BlueRight,
+}
+
+impl StartingTile {
+	/// Gets the position of a robot starting in the middle of the tile, facing the opposing alliance
+	pub fn position(self) -> Position {
+		let last: u8 = TILES_PER_SIDE - 1;
+
+		let (column, row, heading): (u8, u8, f64) = match self {
+			StartingTile::RedLeft => (1, 0, 0.0),
+			StartingTile::RedRight => (last - 1, 0, 0.0),
+			StartingTile::BlueLeft => (last - 1, last, 180.0),
+			StartingTile::BlueRight => (1, last, 180.0),
+		};
+
+		Position(tile_centre(column, row), Angle::new::<degree>(heading))
+	}
+
+	/// Whether the tile belongs to the red alliance
+	pub fn is_red(self) -> bool { matches!(self, StartingTile::RedLeft | StartingTile::RedRight) }
+}
+
+/// Perimeter wall of the field, named as seen by the red drivers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wall {
+	/// Wall along the y axis, at x = 0
+	Left,
+	/// Wall along the y axis, at x = [`FIELD_SIZE`]
+	Right,
+	/// Red alliance wall, along the x axis at y = 0
+	Red,
+	/// Blue alliance wall, along the x axis at y = [`FIELD_SIZE`]
+	Blue,
+}
+
+impl Wall {
+	/// Every wall around the field
+	pub const ALL: [Wall; 4] = [Wall::Left, Wall::Right, Wall::Red, Wall::Blue];
+
+	/// Whether the wall runs along the y axis, so that distances to it fix the x coordinate
+	pub fn is_side(self) -> bool { matches!(self, Wall::Left | Wall::Right) }
+
+	/// Coordinate of the wall along the axis it fixes
+	pub fn coordinate(self) -> Length {
+		match self {
+			Wall::Left | Wall::Red => Length::ZERO,
+			Wall::Right | Wall::Blue => FIELD_SIZE,
+		}
+	}
+}
+
+/// Finds the first wall hit by a ray cast from a position along its heading, and how far away it is
+///
+/// Returns `None` if the position is outside the field.
+pub fn cast_to_wall(position: Position) -> Option<(Wall, Length)> {
+	let Position(origin, heading) = position;
+	let direction_x: Ratio = heading.sin();
+	let direction_y: Ratio = heading.cos();
+
+	Wall::ALL
+		.iter()
+		.filter_map(|&wall| {
+			let (start, direction): (Length, Ratio) = if wall.is_side() {
+				(origin.x, direction_x)
+			} else {
+				(origin.y, direction_y)
+			};
+
+			let distance: Length = (wall.coordinate() - start) / direction;
+
+			(distance >= Length::ZERO && distance.is_finite()).then_some((wall, distance))
+		})
+		.min_by(|(_, a), (_, b)| a.value.total_cmp(&b.value))
+		.filter(|_| (Length::ZERO..=FIELD_SIZE).contains(&origin.x) && (Length::ZERO..=FIELD_SIZE).contains(&origin.y))
+}
+
+/// Straight edge on the field which blocks distance sensors, such as a wall or the side of a game element
+#[derive(Clone, Copy, Debug)]
+pub struct Segment {
+	/// One end of the segment
+	pub start: Coordinates,
+	/// The other end of the segment
+	pub end: Coordinates,
+}
+
+impl Segment {
+	/// Creates a segment between two points
+	pub fn new(start: Coordinates, end: Coordinates) -> Self { Self { start, end } }
+
+	/// Distance along a ray from an origin, with a direction given as a unit vector, to where it crosses the segment
+	fn intersect(&self, origin: Coordinates, direction_x: f64, direction_y: f64) -> Option<f64> {
+		let edge_x: f64 = (self.end.x - self.start.x).value;
+		let edge_y: f64 = (self.end.y - self.start.y).value;
+		let offset_x: f64 = (self.start.x - origin.x).value;
+		let offset_y: f64 = (self.start.y - origin.y).value;
+
+		let denominator: f64 = direction_x * edge_y - direction_y * edge_x;
+
+		if fabs(denominator) < f64::EPSILON {
+			return None;
+		}
+
+		let distance: f64 = (offset_x * edge_y - offset_y * edge_x) / denominator;
+		let along: f64 = (offset_x * direction_y - offset_y * direction_x) / denominator;
+
+		(distance >= 0.0 && (0.0..=1.0).contains(&along)).then_some(distance)
+	}
+}
+
+/// Layout of everything on the field a distance sensor can see, as line segments
+///
+/// Starts out with the perimeter walls, fixed game elements are added as segments or polygons in field coordinates.
+#[derive(Clone, Debug)]
+pub struct FieldMap {
+	segments: Vec<Segment>,
+}
+
+impl Default for FieldMap {
+	fn default() -> Self {
+		let corners: [Coordinates; 4] = [
+			Coordinates::new(Length::ZERO, Length::ZERO),
+			Coordinates::new(FIELD_SIZE, Length::ZERO),
+			Coordinates::new(FIELD_SIZE, FIELD_SIZE),
+			Coordinates::new(Length::ZERO, FIELD_SIZE),
+		];
+
+		let mut map: Self = Self { segments: Vec::new() };
+		map.add_polygon(&corners);
+		map
+	}
+}
+
+impl FieldMap {
+	/// Creates a map of the empty field, with only the perimeter walls
+	pub fn new() -> Self { Self::default() }
+
+	/// Adds a straight edge to the map
+	pub fn add_segment(&mut self, segment: Segment) { self.segments.push(segment); }
+
+	/// Adds the outline of a closed shape to the map, joining each corner to the next and the last back to the first
+	pub fn add_polygon(&mut self, corners: &[Coordinates]) {
+		for (index, &start) in corners.iter().enumerate() {
+			let end: Coordinates = corners[(index + 1) % corners.len()];
+			self.add_segment(Segment::new(start, end));
+		}
+	}
+
+	/// Finds the distance from a position to the nearest segment along its heading
+	///
+	/// Every segment is checked, so the cost grows with the size of the map.
+	pub fn cast(&self, position: Position) -> Option<Length> {
+		let Position(origin, heading) = position;
+		let direction_x: f64 = heading.sin().value;
+		let direction_y: f64 = heading.cos().value;
+
+		self.segments
+			.iter()
+			.filter_map(|segment| segment.intersect(origin, direction_x, direction_y))
+			.min_by(f64::total_cmp)
+			.map(Length::new::<meter>)
+	}
+}
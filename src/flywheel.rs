@@ -0,0 +1,293 @@
+//! Controllers for flywheel launchers, and metrics for comparing how quickly they recover after a shot
+//!
+//! A flywheel only ever needs to spin one way and loses a chunk of its speed each time it launches something, so
+//! simpler controllers than a full PID often do as well or better. [`TakeBackHalf`] and [`BangBang`] share the
+//! [`VelocityControl`] interface with [`VelocityController`](crate::pid::VelocityController), and a
+//! [`RecoveryMetrics`] watching any of them measures how far the speed dips and how long it takes to come back.
+
+use alloc::vec::Vec;
+use core::ops::Div;
+
+use uom::{
+	si::f64::{Angle, AngularVelocity, ElectricPotential, Time},
+	ConstZero,
+};
+
+#[cfg(feature = "vex-rt")]
+use crate::clock::RtosClock;
+use crate::{clock::Clock, motor::MAX_VOLTAGE, pid::VelocityControl};
+
+/// Voltage added per unit of error built up over time
+pub type TakeBackHalfGain = <ElectricPotential as Div<Angle>>::Output;
+
+/// Take-back-half controller, which integrates the error into the voltage and halves the voltage back towards where it
+/// last crossed the target each time the error changes sign
+///
+/// The voltage converges on the voltage which holds the target without needing to be tuned beyond its single gain, at
+/// the cost of recovering more slowly than a well tuned PID.
+pub struct TakeBackHalf<C> {
+	output: ElectricPotential,
+	take_back_half: ElectricPotential,
+	previous_error: Option<AngularVelocity>,
+	previous_time: Time,
+
+	target: AngularVelocity,
+
+	gain: TakeBackHalfGain,
+
+	target_threshold: AngularVelocity,
+
+	clock: C,
+}
+
+#[cfg(feature = "vex-rt")]
+impl TakeBackHalf<RtosClock> {
+	/// Creates a controller which measures time using the RTOS clock
+	pub fn new(target: AngularVelocity, gain: TakeBackHalfGain, target_threshold: AngularVelocity) -> Self {
+		Self::with_clock(target, gain, target_threshold, RtosClock)
+	}
+}
+
+impl<C: Clock> TakeBackHalf<C> {
+	/// Creates a controller which measures time using the given clock
+	pub fn with_clock(
+		target: AngularVelocity, gain: TakeBackHalfGain, target_threshold: AngularVelocity, clock: C,
+	) -> Self {
+		Self {
+			output: ElectricPotential::ZERO,
+			take_back_half: ElectricPotential::ZERO,
+			previous_error: None,
+			previous_time: clock.now(),
+
+			target,
+
+			gain,
+
+			target_threshold,
+
+			clock,
+		}
+	}
+
+	/// Sets an estimate of the voltage which holds the target, which the voltage is halved back towards the first
+	/// time the flywheel reaches the target rather than towards zero
+	pub fn with_holding_estimate(mut self, voltage: ElectricPotential) -> Self {
+		self.take_back_half = voltage;
+		self
+	}
+}
+
+impl<C: Clock> VelocityControl for TakeBackHalf<C> {
+	fn set_target(&mut self, target: AngularVelocity) { self.target = target; }
+
+	fn is_at_speed(&self, current: AngularVelocity) -> bool { (self.target - current).abs() <= self.target_threshold }
+
+	fn cycle(&mut self, current: AngularVelocity) -> ElectricPotential {
+		let error: AngularVelocity = self.target - current;
+
+		let now: Time = self.clock.now();
+		let delta_time: Time = now - self.previous_time;
+
+		self.output = (self.output + self.gain * (error * delta_time))
+			.max(ElectricPotential::ZERO)
+			.min(MAX_VOLTAGE);
+
+		let short: bool = error > AngularVelocity::ZERO;
+		let crossed_target: bool =
+			matches!(self.previous_error, Some(previous_error) if short != (previous_error > AngularVelocity::ZERO));
+
+		if crossed_target {
+			self.output = (self.output + self.take_back_half) / 2.0;
+			self.take_back_half = self.output;
+		}
+
+		self.previous_error = Some(error);
+		self.previous_time = now;
+
+		self.output
+	}
+}
+
+/// Bang-bang controller with hysteresis, which applies full voltage while the flywheel is below the target and a lower
+/// voltage once it is above it
+///
+/// Between the two switching points the voltage is left as it was, so noise in the velocity cannot flick it back and
+/// forth. It recovers as fast as the motor allows but holds the target less steadily than the other controllers.
+pub struct BangBang {
+	high: bool,
+
+	target: AngularVelocity,
+
+	hysteresis: AngularVelocity,
+	high_voltage: ElectricPotential,
+	low_voltage: ElectricPotential,
+
+	target_threshold: AngularVelocity,
+}
+
+impl BangBang {
+	/// Creates a controller which switches between full voltage and no voltage as the flywheel passes the target
+	pub fn new(target: AngularVelocity, target_threshold: AngularVelocity) -> Self {
+		Self {
+			high: true,
+
+			target,
+
+			hysteresis: AngularVelocity::ZERO,
+			high_voltage: MAX_VOLTAGE,
+			low_voltage: ElectricPotential::ZERO,
+
+			target_threshold,
+		}
+	}
+
+	/// Sets how far past the target the flywheel has to go either way before the voltage switches
+	pub fn with_hysteresis(mut self, hysteresis: AngularVelocity) -> Self {
+		self.hysteresis = hysteresis.abs();
+		self
+	}
+
+	/// Sets the voltages applied below and above the target, which default to full voltage and none
+	///
+	/// A low voltage just under the voltage which holds the target makes the flywheel slow down more gently between
+	/// shots, so the voltage switches less often.
+	pub fn with_voltages(mut self, high_voltage: ElectricPotential, low_voltage: ElectricPotential) -> Self {
+		self.high_voltage = high_voltage;
+		self.low_voltage = low_voltage;
+		self
+	}
+}
+
+impl VelocityControl for BangBang {
+	fn set_target(&mut self, target: AngularVelocity) { self.target = target; }
+
+	fn is_at_speed(&self, current: AngularVelocity) -> bool { (self.target - current).abs() <= self.target_threshold }
+
+	fn cycle(&mut self, current: AngularVelocity) -> ElectricPotential {
+		let error: AngularVelocity = self.target - current;
+
+		if error > self.hysteresis {
+			self.high = true;
+		} else if error < -self.hysteresis {
+			self.high = false;
+		}
+
+		if self.high {
+			self.high_voltage
+		} else {
+			self.low_voltage
+		}
+	}
+}
+
+/// How a flywheel recovered from a single shot
+#[derive(Clone, Copy, Debug)]
+pub struct Recovery {
+	/// Furthest the velocity fell below the target
+	pub dip: AngularVelocity,
+	/// Time from the velocity falling out of the threshold of the target to it coming back within it
+	pub recovery_time: Time,
+}
+
+/// Record of how a flywheel recovers from each shot, so that controllers can be compared shot for shot
+///
+/// A dip is taken to start when the velocity falls out of the threshold below the target after being at speed, and
+/// to end once the velocity is back within the threshold. Only dips deeper than the shot threshold are counted as
+/// shots, so that the ripple of a bang-bang controller is not mistaken for one.
+pub struct RecoveryMetrics<C> {
+	target_threshold: AngularVelocity,
+	shot_threshold: AngularVelocity,
+
+	at_speed: bool,
+	dip: Option<(Time, AngularVelocity)>,
+	recoveries: Vec<Recovery>,
+
+	clock: C,
+}
+
+#[cfg(feature = "vex-rt")]
+impl RecoveryMetrics<RtosClock> {
+	/// Creates an empty record which measures time using the RTOS clock
+	pub fn new(target_threshold: AngularVelocity) -> Self { Self::with_clock(target_threshold, RtosClock) }
+}
+
+impl<C: Clock> RecoveryMetrics<C> {
+	/// Creates an empty record which measures time using the given clock
+	pub fn with_clock(target_threshold: AngularVelocity, clock: C) -> Self {
+		Self {
+			target_threshold,
+			shot_threshold: target_threshold,
+
+			at_speed: false,
+			dip: None,
+			recoveries: Vec::new(),
+
+			clock,
+		}
+	}
+
+	/// Sets how far below the target the velocity has to fall for a dip to count as a shot, which defaults to the
+	/// target threshold
+	pub fn with_shot_threshold(mut self, shot_threshold: AngularVelocity) -> Self {
+		self.shot_threshold = shot_threshold;
+		self
+	}
+
+	/// Watches the velocity for a cycle, returning how the flywheel recovered if this cycle finished recovering from
+	/// a shot
+	pub fn update(&mut self, target: AngularVelocity, current: AngularVelocity) -> Option<Recovery> {
+		let error: AngularVelocity = target - current;
+		let within: bool = error.abs() <= self.target_threshold;
+
+		match self.dip {
+			Some((start, dip)) if within => {
+				self.dip = None;
+
+				if dip < self.shot_threshold {
+					return None;
+				}
+
+				let recovery: Recovery = Recovery {
+					dip,
+					recovery_time: self.clock.now() - start,
+				};
+
+				self.recoveries.push(recovery);
+				return Some(recovery);
+			},
+			Some((start, dip)) => self.dip = Some((start, dip.max(error))),
+			None if self.at_speed && error > self.target_threshold => self.dip = Some((self.clock.now(), error)),
+			None => self.at_speed = within,
+		}
+
+		None
+	}
+
+	/// Gets every recovery measured so far, oldest first
+	pub fn recoveries(&self) -> &[Recovery] { &self.recoveries }
+
+	/// Gets the mean time taken to recover, if any shots have been measured
+	pub fn mean_recovery_time(&self) -> Option<Time> {
+		let total: Time = self
+			.recoveries
+			.iter()
+			.fold(Time::ZERO, |total, recovery| total + recovery.recovery_time);
+
+		(!self.recoveries.is_empty()).then(|| total / self.recoveries.len() as f64)
+	}
+
+	/// Gets the largest dip below the target, if any shots have been measured
+	pub fn worst_dip(&self) -> Option<AngularVelocity> {
+		self.recoveries
+			.iter()
+			.map(|recovery| recovery.dip)
+			.reduce(AngularVelocity::max)
+	}
+
+	/// Forgets every recovery measured, such as before trying another controller
+	pub fn clear(&mut self) {
+		self.recoveries.clear();
+		self.dip = None;
+		self.at_speed = false;
+	}
+}
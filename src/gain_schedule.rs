@@ -0,0 +1,151 @@
+//! Gain scheduling, which changes the gains of a [`Pid`] as the conditions it runs under change
+//!
+//! A [`GainSchedule`] holds gains tuned at a handful of points and interpolates linearly between them, so that a turn
+//! of 10° can run with much stiffer gains than a turn of 180°, or an arm can be given more gain when it is carrying
+//! something.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use libm::fabs;
+
+use crate::{
+	clock::Clock,
+	pid::{ControllerStatus, Pid, PidGains, PidInput, PidOutput, Signal},
+	Gains,
+};
+
+/// Gains tuned at a set of values of a scheduling variable `K`, for a controller measuring `I` and outputting `O`
+#[derive(Clone)]
+pub struct GainSchedule<K, I: PidInput, O: PidOutput<I>> {
+	/// Points sorted by the value of the scheduling variable, in SI base units
+	points: Vec<(f64, PidGains<I, O>)>,
+	key: PhantomData<K>,
+}
+
+impl<K: Signal, I: PidInput, O: PidOutput<I>> GainSchedule<K, I, O> {
+	/// Creates a schedule which runs with the same gains everywhere until more points are added
+	pub fn new(key: K, gains: PidGains<I, O>) -> Self {
+		Self {
+			points: Vec::from([(key.to_base(), gains)]),
+			key: PhantomData,
+		}
+	}
+
+	/// Adds gains to use at a value of the scheduling variable, replacing any already given for that value
+	pub fn with_point(mut self, key: K, gains: PidGains<I, O>) -> Self {
+		let key: f64 = key.to_base();
+
+		match self.points.binary_search_by(|(point, _)| point.total_cmp(&key)) {
+			Ok(index) => self.points[index].1 = gains,
+			Err(index) => self.points.insert(index, (key, gains)),
+		}
+
+		self
+	}
+
+	/// Gets the gains for a value of the scheduling variable
+	///
+	/// Between two points each gain is interpolated linearly, and beyond the first or last point the gains of that
+	/// point are used.
+	pub fn gains(&self, key: K) -> PidGains<I, O> {
+		let key: f64 = key.to_base();
+
+		let index: usize = self.points.partition_point(|(point, _)| *point < key);
+
+		let (upper_key, upper) = match self.points.get(index) {
+			Some(point) => *point,
+			None => return self.points[self.points.len() - 1].1,
+		};
+
+		let (lower_key, lower) = match index.checked_sub(1) {
+			Some(index) => self.points[index],
+			None => return upper,
+		};
+
+		let weight: f64 = (key - lower_key) / (upper_key - lower_key);
+		let mix = |lower: f64, upper: f64| lower + (upper - lower) * weight;
+
+		Gains {
+			proportional: Signal::from_base(mix(lower.proportional.to_base(), upper.proportional.to_base())),
+			integral: Signal::from_base(mix(lower.integral.to_base(), upper.integral.to_base())),
+			derivative: Signal::from_base(mix(lower.derivative.to_base(), upper.derivative.to_base())),
+		}
+	}
+}
+
+/// Variable a [`ScheduledPid`] looks its gains up by
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ScheduleVariable {
+	ErrorMagnitude,
+	Target,
+	External,
+}
+
+/// [`Pid`] whose gains are looked up from a [`GainSchedule`] before every cycle
+///
+/// The integral is kept as the gains change, so a large step in the integral gain between neighbouring points shows
+/// up as a step in the output.
+pub struct ScheduledPid<K, I: PidInput, O: PidOutput<I>, C> {
+	pid: Pid<I, O, C>,
+	schedule: GainSchedule<K, I, O>,
+	variable: ScheduleVariable,
+	external: f64,
+}
+
+impl<I: PidInput, O: PidOutput<I>, C: Clock> ScheduledPid<I, I, O, C> {
+	/// Creates a controller scheduled on the size of the error either way, such as to run stiffer gains over short
+	/// moves
+	pub fn on_error(pid: Pid<I, O, C>, schedule: GainSchedule<I, I, O>) -> Self {
+		Self::with_variable(pid, schedule, ScheduleVariable::ErrorMagnitude, 0.0)
+	}
+
+	/// Creates a controller scheduled on the target, such as to hold an arm harder the further it is raised
+	pub fn on_target(pid: Pid<I, O, C>, schedule: GainSchedule<I, I, O>) -> Self {
+		Self::with_variable(pid, schedule, ScheduleVariable::Target, 0.0)
+	}
+}
+
+impl<K: Signal, I: PidInput, O: PidOutput<I>, C: Clock> ScheduledPid<K, I, O, C> {
+	/// Creates a controller scheduled on a variable it is told about, such as the load the mechanism is carrying,
+	/// starting from the given value of the variable
+	pub fn on_variable(pid: Pid<I, O, C>, schedule: GainSchedule<K, I, O>, variable: K) -> Self {
+		Self::with_variable(pid, schedule, ScheduleVariable::External, variable.to_base())
+	}
+
+	fn with_variable(
+		pid: Pid<I, O, C>, schedule: GainSchedule<K, I, O>, variable: ScheduleVariable, external: f64,
+	) -> Self {
+		Self {
+			pid,
+			schedule,
+			variable,
+			external,
+		}
+	}
+
+	/// Sets the value of the scheduling variable for controllers created with [`on_variable`](Self::on_variable),
+	/// which is used from the next cycle on
+	pub fn set_variable(&mut self, variable: K) { self.external = variable.to_base(); }
+
+	/// Gets how far the controller has got towards the target as of the last cycle
+	pub fn status(&self) -> ControllerStatus { self.pid.status() }
+
+	/// Sets the current target that the controller is aiming for, starting the settling conditions over
+	pub fn set_target(&mut self, target: I) { self.pid.set_target(target); }
+
+	/// Gets the controller being scheduled, such as to read the gains it last ran with
+	pub fn pid(&self) -> &Pid<I, O, C> { &self.pid }
+
+	/// Looks up the gains for the current conditions, then runs a cycle of the PID and returns its output
+	pub fn cycle(&mut self, current: I) -> O {
+		let key: f64 = match self.variable {
+			ScheduleVariable::ErrorMagnitude => fabs(self.pid.target().to_base() - current.to_base()),
+			ScheduleVariable::Target => self.pid.target().to_base(),
+			ScheduleVariable::External => self.external,
+		};
+
+		self.pid.set_gains(self.schedule.gains(K::from_base(key)));
+		self.pid.cycle(current)
+	}
+}
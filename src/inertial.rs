@@ -0,0 +1,54 @@
+//! Abstraction over inertial sensors used as a heading source, with a mock sensor for running odometry off the robot
+
+use alloc::rc::Rc;
+use core::{cell::Cell, convert::Infallible};
+
+use uom::si::f64::Angle;
+
+/// Sensor which measures how far the robot has rotated
+pub trait InertialSensor {
+	/// Error returned when the sensor cannot be read
+	type Error;
+
+	/// Gets the total clockwise rotation of the robot since the sensor was reset, without wrapping at a full turn
+	fn get_rotation(&self) -> Result<Angle, Self::Error>;
+}
+
+#[cfg(feature = "vex-rt")]
+impl InertialSensor for vex_rt::inertial::InertialSensor {
+	type Error = vex_rt::inertial::InertialSensorError;
+
+	fn get_rotation(&self) -> Result<Angle, Self::Error> { vex_rt::inertial::InertialSensor::get_rotation(self) }
+}
+
+/// Placeholder for systems which have no inertial sensor fitted
+pub enum NoInertialSensor {}
+
+impl InertialSensor for NoInertialSensor {
+	type Error = Infallible;
+
+	fn get_rotation(&self) -> Result<Angle, Self::Error> { match *self {} }
+}
+
+/// In-memory inertial sensor whose rotation is set directly
+///
+/// Clones share the same rotation, so a test or simulation can keep a clone to turn the sensor while an odometry
+/// system owns the other.
+#[derive(Clone, Default)]
+pub struct MockInertialSensor {
+	rotation: Rc<Cell<Angle>>,
+}
+
+impl MockInertialSensor {
+	/// Creates a sensor at zero rotation
+	pub fn new() -> Self { Self::default() }
+
+	/// Turns the sensor clockwise by a relative angle
+	pub fn rotate(&self, angle: Angle) { self.rotation.set(self.rotation.get() + angle); }
+}
+
+impl InertialSensor for MockInertialSensor {
+	type Error = Infallible;
+
+	fn get_rotation(&self) -> Result<Angle, Self::Error> { Ok(self.rotation.get()) }
+}
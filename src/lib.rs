@@ -2,26 +2,43 @@
 #![no_std]
 #![warn(missing_docs)]
 
+#[cfg(feature = "vex-rt")]
 use core::time::Duration;
 
-use uom::si::f64::{Frequency, FrequencyDrift, Ratio};
-
 extern crate alloc;
 
+pub mod autotune;
+pub mod clock;
 pub mod coordinates;
+pub mod distance;
+pub mod encoder;
+pub mod feedforward;
+pub mod field;
+pub mod flywheel;
+pub mod gain_schedule;
+pub mod inertial;
+pub mod localisation;
 mod math;
+pub mod motion_profile;
+pub mod motor;
 pub mod odometry;
 pub mod pid;
 pub mod pure_pursuit;
+pub mod relocalisation;
+pub mod sim;
 pub mod tank_drive;
 pub mod x_drive;
 
 /// Constant for
+#[cfg(feature = "vex-rt")]
 pub(crate) const PID_CYCLE_DURATION: Duration = Duration::from_millis(50);
 
 /// Gains struct containing ratios for
-#[derive(Clone, Copy)]
-pub struct Gains<P = Frequency, I = FrequencyDrift, D = Ratio> {
+///
+/// The units of each gain depend on what the controller measures and outputs, as given by
+/// [`PidGains`](pid::PidGains).
+#[derive(Clone, Copy, Debug)]
+pub struct Gains<P, I, D> {
 	pub proportional: P,
 	pub integral: I,
 	pub derivative: D,
@@ -0,0 +1,311 @@
+//! Monte Carlo localisation, which follows the robot with a cloud of guesses at its position
+//!
+//! Every particle is moved by the change in the odometry position plus some random noise, then weighed by how well
+//! the distance sensors' readings match what they would read from that particle's position on a [`FieldMap`]. Unlikely
+//! particles are replaced with copies of likely ones, so the cloud gathers around where the robot really is even as
+//! the odometry drifts away from it.
+//!
+//! The work done per update is fixed by the number of particles, sensors and map segments: each update casts one ray
+//! per particle per sensor against every segment. The particle count is chosen up front and nothing is allocated
+//! after construction, so the filter can run at a steady rate alongside the drive loop.
+
+use alloc::{vec, vec::Vec};
+use core::f64::consts::TAU;
+
+use libm::{atan2, cos, exp, fabs, log, sin, sqrt};
+use uom::si::{
+	angle::radian,
+	f64::{Angle, Length, Ratio},
+	length::{inch, meter},
+	ratio::ratio,
+};
+
+use crate::{
+	coordinates::Position,
+	distance::{DistanceSensor, SensorMount},
+	field::FieldMap,
+};
+
+/// Random noise added to the odometry movement when moving the particles
+#[derive(Clone, Copy, Debug)]
+pub struct MotionNoise {
+	/// Standard deviation of the error in distance travelled, as a ratio of the distance
+	pub translation: Ratio,
+	/// Standard deviation of the error in rotation, as a ratio of the rotation
+	pub rotation: Ratio,
+	/// Standard deviation of the heading drift per metre travelled, in radians
+	pub drift: Ratio,
+}
+
+impl Default for MotionNoise {
+	fn default() -> Self {
+		Self {
+			translation: Ratio::new::<ratio>(0.05),
+			rotation: Ratio::new::<ratio>(0.05),
+			drift: Ratio::new::<ratio>(0.02),
+		}
+	}
+}
+
+/// Xorshift64* pseudorandom number generator, which is small and fast enough to run on the brain
+struct Random(u64);
+
+impl Random {
+	fn new(seed: u64) -> Self { Self(if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed }) }
+
+	fn next_u64(&mut self) -> u64 {
+		self.0 ^= self.0 >> 12;
+		self.0 ^= self.0 << 25;
+		self.0 ^= self.0 >> 27;
+		self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+	}
+
+	/// Uniformly distributed number in [0, 1)
+	fn uniform(&mut self) -> f64 { (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 }
+
+	/// Normally distributed number with zero mean, by the Box-Muller transform
+	fn gaussian(&mut self, deviation: f64) -> f64 {
+		let magnitude: f64 = sqrt(-2.0 * log(1.0 - self.uniform()));
+		magnitude * cos(TAU * self.uniform()) * deviation
+	}
+}
+
+/// Particle filter tracking the position of the robot on the field
+pub struct ParticleFilter<D> {
+	map: FieldMap,
+	sensors: Vec<(D, SensorMount)>,
+
+	particles: Vec<Position>,
+	weights: Vec<f64>,
+	resampled: Vec<Position>,
+	readings: Vec<Option<Length>>,
+
+	random: Random,
+	last_odometry: Option<Position>,
+
+	/// Noise added to the odometry movement
+	pub motion_noise: MotionNoise,
+	/// Standard deviation of distance readings, as a ratio of the distance read
+	pub sensor_noise: Ratio,
+	/// Smallest standard deviation of distance readings, which applies at close range
+	pub minimum_sensor_noise: Length,
+	/// Chance that a reading is of something which is not on the map, such as another robot
+	pub outlier_probability: Ratio,
+	/// Longest distance reading that is used
+	pub max_range: Length,
+}
+
+impl<D> ParticleFilter<D> {
+	/// Creates a filter with a fixed number of particles scattered around a starting position
+	///
+	/// The seed makes runs repeatable, any value gives a usable sequence.
+	pub fn new(
+		map: FieldMap, particle_count: usize, position: Position, spread: Length, heading_spread: Angle, seed: u64,
+	) -> Self {
+		let particle_count: usize = particle_count.max(1);
+
+		let mut filter: Self = Self {
+			map,
+			sensors: Vec::new(),
+			particles: vec![position; particle_count],
+			weights: vec![1.0 / particle_count as f64; particle_count],
+			resampled: vec![position; particle_count],
+			readings: Vec::new(),
+			random: Random::new(seed),
+			last_odometry: None,
+			motion_noise: MotionNoise::default(),
+			sensor_noise: Ratio::new::<ratio>(0.05),
+			minimum_sensor_noise: Length::new::<inch>(0.6),
+			outlier_probability: Ratio::new::<ratio>(0.1),
+			max_range: Length::new::<meter>(2.0),
+		};
+
+		filter.reset(position, spread, heading_spread);
+		filter
+	}
+
+	/// Adds a sensor mounted at a known position on the robot
+	pub fn add_sensor(&mut self, sensor: D, mount: SensorMount) {
+		self.sensors.push((sensor, mount));
+		self.readings.push(None);
+	}
+
+	/// Scatters the particles around a position again, with normally distributed errors
+	pub fn reset(&mut self, position: Position, spread: Length, heading_spread: Angle) {
+		let Position(coordinates, heading) = position;
+		let weight: f64 = 1.0 / self.particles.len() as f64;
+
+		for (particle, particle_weight) in self.particles.iter_mut().zip(self.weights.iter_mut()) {
+			*particle = Position::new(
+				coordinates.x + Length::new::<meter>(self.random.gaussian(spread.get::<meter>())),
+				coordinates.y + Length::new::<meter>(self.random.gaussian(spread.get::<meter>())),
+				heading + Angle::new::<radian>(self.random.gaussian(heading_spread.get::<radian>())),
+			);
+			*particle_weight = weight;
+		}
+	}
+
+	/// Gets the weighted average position of the particles
+	pub fn estimate(&self) -> Position {
+		let Position(_, reference) = self.particles[0];
+
+		let mut x: f64 = 0.0;
+		let mut y: f64 = 0.0;
+		let mut heading_sin: f64 = 0.0;
+		let mut heading_cos: f64 = 0.0;
+
+		for (Position(coordinates, heading), weight) in self.particles.iter().zip(self.weights.iter()) {
+			// Headings are averaged as directions relative to one particle so that the estimate keeps counting past a
+			// full turn like the odometry does
+			let relative: f64 = (*heading - reference).get::<radian>();
+
+			x += coordinates.x.get::<meter>() * weight;
+			y += coordinates.y.get::<meter>() * weight;
+			heading_sin += sin(relative) * weight;
+			heading_cos += cos(relative) * weight;
+		}
+
+		Position::new(
+			Length::new::<meter>(x),
+			Length::new::<meter>(y),
+			reference + Angle::new::<radian>(atan2(heading_sin, heading_cos)),
+		)
+	}
+
+	/// Moves every particle by a robot-relative movement plus noise
+	fn predict(&mut self, forward: f64, sideways: f64, rotation: f64) {
+		let distance: f64 = sqrt(forward * forward + sideways * sideways);
+		let translation_deviation: f64 = self.motion_noise.translation.get::<ratio>();
+		let rotation_deviation: f64 = self.motion_noise.rotation.get::<ratio>() * fabs(rotation)
+			+ self.motion_noise.drift.get::<ratio>() * distance;
+
+		for particle in self.particles.iter_mut() {
+			// Sideways slip scales with the whole movement, since a robot driving straight can still be knocked aside
+			let sideways: f64 = sideways + self.random.gaussian(translation_deviation * distance);
+			let forward: f64 = forward + self.random.gaussian(translation_deviation * fabs(forward));
+			let rotation: f64 = rotation + self.random.gaussian(rotation_deviation);
+
+			let Position(coordinates, heading) = *particle;
+			let average_heading: f64 = heading.get::<radian>() + rotation / 2.0;
+
+			*particle = Position::new(
+				coordinates.x + Length::new::<meter>(forward * sin(average_heading) + sideways * cos(average_heading)),
+				coordinates.y + Length::new::<meter>(forward * cos(average_heading) - sideways * sin(average_heading)),
+				heading + Angle::new::<radian>(rotation),
+			);
+		}
+	}
+
+	/// Redraws the particles in proportion to their weights by systematic resampling
+	fn resample(&mut self) {
+		let count: usize = self.particles.len();
+		let step: f64 = 1.0 / count as f64;
+		let mut target: f64 = self.random.uniform() * step;
+		let mut cumulative: f64 = self.weights[0];
+		let mut source: usize = 0;
+
+		for resampled in self.resampled.iter_mut() {
+			while target > cumulative && source + 1 < count {
+				source += 1;
+				cumulative += self.weights[source];
+			}
+
+			*resampled = self.particles[source];
+			target += step;
+		}
+
+		core::mem::swap(&mut self.particles, &mut self.resampled);
+		self.weights.fill(step);
+	}
+}
+
+impl<D: DistanceSensor> ParticleFilter<D> {
+	/// Moves the particles by the change in the odometry position since the last update, weighs them against the
+	/// distance sensors and returns the new estimate of the robot's position
+	///
+	/// Should be called with [`OdometrySystem::get_position`](crate::odometry::OdometrySystem::get_position) after
+	/// every odometry cycle. The particles are only weighed once the robot moves, so that a robot sitting still does
+	/// not collapse the cloud onto a handful of particles.
+	pub fn update(&mut self, odometry: Position) -> Position {
+		let last_odometry: Position = self.last_odometry.replace(odometry).unwrap_or(odometry);
+
+		let Position(last_coordinates, last_heading) = last_odometry;
+		let Position(coordinates, heading) = odometry;
+
+		let change_x: f64 = (coordinates.x - last_coordinates.x).get::<meter>();
+		let change_y: f64 = (coordinates.y - last_coordinates.y).get::<meter>();
+		let rotation: f64 = (heading - last_heading).get::<radian>();
+
+		if change_x == 0.0 && change_y == 0.0 && rotation == 0.0 {
+			return self.estimate();
+		}
+
+		// Taken apart at the heading halfway through the movement, which is the heading the particles put it back
+		// together at
+		let average_heading: f64 = last_heading.get::<radian>() + rotation / 2.0;
+		let forward: f64 = change_x * sin(average_heading) + change_y * cos(average_heading);
+		let sideways: f64 = change_x * cos(average_heading) - change_y * sin(average_heading);
+
+		self.predict(forward, sideways, rotation);
+		self.weigh();
+
+		let effective_count: f64 = 1.0 / self.weights.iter().map(|weight| weight * weight).sum::<f64>();
+
+		if effective_count < self.particles.len() as f64 / 2.0 {
+			self.resample();
+		}
+
+		self.estimate()
+	}
+
+	/// Multiplies each particle's weight by the likelihood of the current readings from its position
+	fn weigh(&mut self) {
+		let max_range: f64 = self.max_range.get::<meter>();
+
+		for ((sensor, _), reading) in self.sensors.iter().zip(self.readings.iter_mut()) {
+			*reading = sensor
+				.get_distance()
+				.ok()
+				.filter(|distance| distance.get::<meter>() <= max_range);
+		}
+
+		if self.readings.iter().all(Option::is_none) {
+			return;
+		}
+
+		let outlier: f64 = self.outlier_probability.get::<ratio>().clamp(0.0, 1.0);
+		let noise: f64 = self.sensor_noise.get::<ratio>();
+		let minimum_noise: f64 = self.minimum_sensor_noise.get::<meter>();
+
+		for (particle, weight) in self.particles.iter().zip(self.weights.iter_mut()) {
+			for ((_, mount), reading) in self.sensors.iter().zip(self.readings.iter()) {
+				let reading: f64 = match reading {
+					Some(reading) => reading.get::<meter>(),
+					None => continue,
+				};
+
+				let likelihood: f64 = match self.map.cast(mount.locate(*particle)) {
+					Some(expected) => {
+						let expected: f64 = expected.get::<meter>();
+						let deviation: f64 = (noise * expected).max(minimum_noise);
+						let error: f64 = (reading - expected) / deviation;
+
+						(1.0 - outlier) * exp(-0.5 * error * error) / (deviation * sqrt(TAU))
+					},
+					None => 0.0,
+				};
+
+				*weight *= likelihood + outlier / max_range;
+			}
+		}
+
+		let total: f64 = self.weights.iter().sum();
+
+		if total > 0.0 && total.is_finite() {
+			self.weights.iter_mut().for_each(|weight| *weight /= total);
+		} else {
+			let count: f64 = self.weights.len() as f64;
+			self.weights.fill(1.0 / count);
+		}
+	}
+}
@@ -1,6 +1,6 @@
 use core::marker::PhantomData;
 
-use libm::{acos, asin, atan, atan2, cos, sin, sqrt, tan};
+use libm::{acos, asin, atan, atan2, cos, fabs, sin, sqrt, tan};
 use uom::{
 	num_traits::Num,
 	si::{
@@ -197,3 +197,62 @@ where
 
 	fn atan(self) -> Self::Angle { Self::Angle::new::<radian>(self.get::<ratio>().atan()) }
 }
+
+/// Solves a square linear system by Gaussian elimination with partial pivoting, returning `None` if it is singular
+pub(crate) fn solve_linear<const N: usize>(mut matrix: [[f64; N]; N], mut vector: [f64; N]) -> Option<[f64; N]> {
+	let scale: f64 = matrix
+		.iter()
+		.flatten()
+		.fold(0.0, |scale: f64, value| scale.max(fabs(*value)));
+
+	for column in 0..N {
+		let pivot: usize = (column..N).max_by(|&a, &b| fabs(matrix[a][column]).total_cmp(&fabs(matrix[b][column])))?;
+
+		if fabs(matrix[pivot][column]) <= scale * 1e-9 {
+			return None;
+		}
+
+		matrix.swap(column, pivot);
+		vector.swap(column, pivot);
+
+		let pivot_row: [f64; N] = matrix[column];
+
+		for row in column + 1..N {
+			let factor: f64 = matrix[row][column] / pivot_row[column];
+
+			for (value, pivot_value) in matrix[row].iter_mut().zip(pivot_row).skip(column) {
+				*value -= factor * pivot_value;
+			}
+
+			vector[row] -= factor * vector[column];
+		}
+	}
+
+	let mut solution: [f64; N] = [0.0; N];
+
+	for row in (0..N).rev() {
+		let known: f64 = (row + 1..N).map(|index| matrix[row][index] * solution[index]).sum();
+		solution[row] = (vector[row] - known) / matrix[row][row];
+	}
+
+	Some(solution)
+}
+
+/// Finds the least squares solution to an overdetermined linear system given as rows of coefficients and their
+/// values, returning `None` if the rows do not pin down every unknown
+pub(crate) fn least_squares<const N: usize>(rows: impl Iterator<Item = ([f64; N], f64)>) -> Option<[f64; N]> {
+	let mut normal: [[f64; N]; N] = [[0.0; N]; N];
+	let mut projected: [f64; N] = [0.0; N];
+
+	for (coefficients, value) in rows {
+		for i in 0..N {
+			projected[i] += coefficients[i] * value;
+
+			for j in 0..N {
+				normal[i][j] += coefficients[i] * coefficients[j];
+			}
+		}
+	}
+
+	solve_linear(normal, projected)
+}
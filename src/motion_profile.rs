@@ -0,0 +1,273 @@
+//! Motion profiles, which turn a move into a smooth series of position, velocity and acceleration setpoints over time
+//!
+//! Handing a controller the whole move as a step makes it ask for as much as it can straight away, so the robot jerks
+//! and its wheels slip. Following a profile instead ramps the velocity up and down within limits the mechanism can
+//! keep up with. A trapezoidal profile limits the velocity and acceleration, and an S-curve profile also limits the
+//! jerk, the rate the acceleration changes at, which smooths out the corners of the trapezoid.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use libm::{cbrt, fabs, sqrt};
+use uom::si::{f64::Time, time::second};
+
+use crate::pid::{PidInput, Signal};
+
+/// Quantity a [`MotionProfile`] can move through, along with the quantities its derivatives are measured in
+pub trait ProfileQuantity: PidInput {
+	/// Rate of change of the quantity
+	type Velocity: Signal;
+	/// Rate of change of the velocity
+	type Acceleration: Signal;
+	/// Rate of change of the acceleration
+	type Jerk: Signal;
+}
+
+impl<T> ProfileQuantity for T
+where
+	T: PidInput,
+	T::Rate: PidInput,
+	<T::Rate as PidInput>::Rate: PidInput,
+{
+	type Acceleration = <T::Rate as PidInput>::Rate;
+	type Jerk = <<T::Rate as PidInput>::Rate as PidInput>::Rate;
+	type Velocity = T::Rate;
+}
+
+/// Limits a [`MotionProfile`] keeps within
+#[derive(Clone, Copy, Debug)]
+pub struct Constraints<I: ProfileQuantity> {
+	/// Fastest the profile moves either way
+	pub max_velocity: I::Velocity,
+	/// Fastest the profile speeds up or slows down
+	pub max_acceleration: I::Acceleration,
+	/// Fastest the acceleration changes, without which the acceleration steps straight to its limit as in a
+	/// trapezoidal profile
+	pub max_jerk: Option<I::Jerk>,
+}
+
+/// Where a [`MotionProfile`] is at a moment in time
+#[derive(Clone, Copy, Debug)]
+pub struct Setpoint<I: ProfileQuantity> {
+	/// Position relative to the start of the move
+	pub position: I,
+	/// Velocity the position is changing at
+	pub velocity: I::Velocity,
+	/// Acceleration the velocity is changing at
+	pub acceleration: I::Acceleration,
+}
+
+/// Position, velocity and acceleration in SI base units
+#[derive(Clone, Copy, Default)]
+struct State {
+	position: f64,
+	velocity: f64,
+	acceleration: f64,
+}
+
+impl State {
+	/// Moves the state forward through a period of constant jerk
+	fn advance(self, time: f64, jerk: f64) -> Self {
+		Self {
+			position: self.position
+				+ self.velocity * time
+				+ self.acceleration * time * time / 2.0
+				+ jerk * time * time * time / 6.0,
+			velocity: self.velocity + self.acceleration * time + jerk * time * time / 2.0,
+			acceleration: self.acceleration + jerk * time,
+		}
+	}
+}
+
+/// Part of a profile over which the jerk is constant
+#[derive(Clone, Copy)]
+struct Segment {
+	start_time: f64,
+	start: State,
+	jerk: f64,
+}
+
+/// Move from rest to rest over a given distance, sampled by the time since it started
+#[derive(Clone)]
+pub struct MotionProfile<I: ProfileQuantity> {
+	segments: Vec<Segment>,
+	duration: f64,
+	end: State,
+	direction: f64,
+	quantity: PhantomData<I>,
+}
+
+impl<I: ProfileQuantity> MotionProfile<I> {
+	/// Creates a profile over a distance within a set of constraints, which is an S-curve profile if the jerk is
+	/// limited and a trapezoidal profile otherwise
+	pub fn new(distance: I, constraints: Constraints<I>) -> Self {
+		match constraints.max_jerk {
+			Some(max_jerk) => Self::s_curve(
+				distance,
+				constraints.max_velocity,
+				constraints.max_acceleration,
+				max_jerk,
+			),
+			None => Self::trapezoidal(distance, constraints.max_velocity, constraints.max_acceleration),
+		}
+	}
+
+	/// Creates a profile which accelerates as hard as allowed up to the velocity limit, cruises, then decelerates as
+	/// hard as allowed to stop at the distance
+	///
+	/// Moves too short to reach the velocity limit start decelerating as soon as they stop accelerating, making the
+	/// profile a triangle.
+	pub fn trapezoidal(distance: I, max_velocity: I::Velocity, max_acceleration: I::Acceleration) -> Self {
+		let distance_magnitude: f64 = fabs(distance.to_base());
+		let acceleration: f64 = fabs(max_acceleration.to_base());
+
+		let velocity: f64 = fabs(max_velocity.to_base()).min(sqrt(distance_magnitude * acceleration));
+
+		let accelerating: f64 = velocity / acceleration;
+		let cruising: f64 = distance_magnitude / velocity - accelerating;
+
+		Self::from_phases(
+			distance,
+			[
+				(accelerating, Some(acceleration), 0.0),
+				(cruising, Some(0.0), 0.0),
+				(accelerating, Some(-acceleration), 0.0),
+			],
+		)
+	}
+
+	/// Creates a profile which ramps the acceleration up and down within the jerk limit, so that the acceleration
+	/// never steps
+	///
+	/// Moves too short to reach the velocity or acceleration limits ramp up to whatever peak velocity lets them stop
+	/// at the distance.
+	pub fn s_curve(
+		distance: I, max_velocity: I::Velocity, max_acceleration: I::Acceleration, max_jerk: I::Jerk,
+	) -> Self {
+		let distance_magnitude: f64 = fabs(distance.to_base());
+		let acceleration: f64 = fabs(max_acceleration.to_base());
+		let jerk: f64 = fabs(max_jerk.to_base());
+
+		// Fastest velocity reachable while only ramping the acceleration up and straight back down
+		let ramp_velocity: f64 = acceleration * acceleration / jerk;
+
+		// Time taken and distance covered getting from rest to a velocity and back down again
+		let speed_up_time = |velocity: f64| -> f64 {
+			if velocity <= ramp_velocity {
+				2.0 * sqrt(velocity / jerk)
+			} else {
+				velocity / acceleration + acceleration / jerk
+			}
+		};
+		let speed_up_distance = |velocity: f64| -> f64 { velocity * speed_up_time(velocity) };
+
+		let mut velocity: f64 = fabs(max_velocity.to_base());
+
+		if speed_up_distance(velocity) > distance_magnitude {
+			// Peak velocity which covers the whole distance speeding up and slowing down, taken from whichever shape of
+			// acceleration fits
+			let ramp_only: f64 = cbrt(distance_magnitude * distance_magnitude * jerk / 4.0);
+
+			velocity = if ramp_only <= ramp_velocity {
+				ramp_only
+			} else {
+				let ramp_time: f64 = acceleration / jerk;
+				acceleration * (sqrt(ramp_time * ramp_time + 4.0 * distance_magnitude / acceleration) - ramp_time) / 2.0
+			};
+		}
+
+		let (ramping, holding): (f64, f64) = if velocity <= ramp_velocity {
+			(sqrt(velocity / jerk), 0.0)
+		} else {
+			(acceleration / jerk, velocity / acceleration - acceleration / jerk)
+		};
+
+		let cruising: f64 = if velocity > 0.0 {
+			(distance_magnitude - speed_up_distance(velocity)) / velocity
+		} else {
+			0.0
+		};
+
+		Self::from_phases(
+			distance,
+			[
+				(ramping, None, jerk),
+				(holding, None, 0.0),
+				(ramping, None, -jerk),
+				(cruising, None, 0.0),
+				(ramping, None, -jerk),
+				(holding, None, 0.0),
+				(ramping, None, jerk),
+			],
+		)
+	}
+
+	/// Builds a profile from phases of a duration, an acceleration to start the phase at if it steps and a jerk,
+	/// given for a move in the positive direction
+	fn from_phases<const N: usize>(distance: I, phases: [(f64, Option<f64>, f64); N]) -> Self {
+		let mut segments: Vec<Segment> = Vec::with_capacity(N);
+		let mut state: State = State::default();
+		let mut time: f64 = 0.0;
+
+		for (duration, acceleration, jerk) in phases {
+			// Limits of zero or a move of no distance leave phases which are empty or not numbers, which are skipped
+			if !(duration.is_finite() && duration > 0.0) {
+				continue;
+			}
+
+			if let Some(acceleration) = acceleration {
+				state.acceleration = acceleration;
+			}
+
+			segments.push(Segment {
+				start_time: time,
+				start: state,
+				jerk,
+			});
+
+			state = state.advance(duration, jerk);
+			time += duration;
+		}
+
+		let direction: f64 = if distance.to_base() < 0.0 { -1.0 } else { 1.0 };
+
+		Self {
+			segments,
+			duration: time,
+			end: State {
+				position: fabs(distance.to_base()),
+				velocity: 0.0,
+				acceleration: 0.0,
+			},
+			direction,
+			quantity: PhantomData,
+		}
+	}
+
+	/// Gets how long the profile takes to reach the end of the move
+	pub fn duration(&self) -> Time { Time::new::<second>(self.duration) }
+
+	/// Whether the profile has reached the end of the move by a time since it started
+	pub fn is_finished(&self, time: Time) -> bool { time.get::<second>() >= self.duration }
+
+	/// Gets where the profile is at a time since it started, which is the start before it starts and the end after it
+	/// ends
+	pub fn sample(&self, time: Time) -> Setpoint<I> {
+		let time: f64 = time.get::<second>();
+
+		let state: State = if time >= self.duration {
+			self.end
+		} else {
+			match self.segments.iter().rev().find(|segment| segment.start_time <= time) {
+				Some(segment) => segment.start.advance(time - segment.start_time, segment.jerk),
+				None => State::default(),
+			}
+		};
+
+		Setpoint {
+			position: I::from_base(state.position * self.direction),
+			velocity: I::Velocity::from_base(state.velocity * self.direction),
+			acceleration: I::Acceleration::from_base(state.acceleration * self.direction),
+		}
+	}
+}
@@ -0,0 +1,140 @@
+//! Abstraction over the smart motors that drive trains are built from, so that drive code can run against real
+//! hardware on the brain or against an in-memory mock on the host
+
+use alloc::rc::Rc;
+use core::{cell::Cell, marker::PhantomData};
+
+use uom::{
+	si::f64::{Angle, AngularVelocity, ElectricPotential, Ratio},
+	ConstZero,
+};
+
+/// Highest voltage that can be applied to a V5 smart motor
+pub const MAX_VOLTAGE: ElectricPotential = ElectricPotential {
+	dimension: PhantomData,
+	units: PhantomData,
+	value: 12.0,
+};
+
+/// Motor with an integrated encoder, such as the V5 smart motor
+pub trait SmartMotor {
+	/// Error returned when the motor cannot be read or commanded
+	type Error;
+
+	/// Sets the motor output as a ratio of full power, between -1 and 1
+	fn move_ratio(&mut self, value: Ratio) -> Result<(), Self::Error>;
+
+	/// Sets the voltage applied to the motor
+	fn move_voltage(&mut self, voltage: ElectricPotential) -> Result<(), Self::Error>;
+
+	/// Sets the current position of the motor as its zero position
+	fn tare_position(&mut self) -> Result<(), Self::Error>;
+
+	/// Gets the position of the motor relative to its zero position
+	fn get_position(&self) -> Result<Angle, Self::Error>;
+
+	/// Gets the velocity the motor is currently moving at
+	fn get_actual_velocity(&self) -> Result<AngularVelocity, Self::Error>;
+}
+
+#[cfg(feature = "vex-rt")]
+impl SmartMotor for vex_rt::prelude::Motor {
+	type Error = vex_rt::prelude::MotorError;
+
+	fn move_ratio(&mut self, value: Ratio) -> Result<(), Self::Error> {
+		vex_rt::prelude::Motor::move_ratio(self, value)
+	}
+
+	fn move_voltage(&mut self, voltage: ElectricPotential) -> Result<(), Self::Error> {
+		vex_rt::prelude::Motor::move_voltage(self, voltage)
+	}
+
+	fn tare_position(&mut self) -> Result<(), Self::Error> { vex_rt::prelude::Motor::tare_position(self) }
+
+	fn get_position(&self) -> Result<Angle, Self::Error> { vex_rt::prelude::Motor::get_position(self) }
+
+	fn get_actual_velocity(&self) -> Result<AngularVelocity, Self::Error> {
+		vex_rt::prelude::Motor::get_actual_velocity(self)
+	}
+}
+
+/// Error returned by a [`MockMotor`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MockMotorError {
+	/// The motor has been marked as unplugged with [`MockMotor::set_disconnected`]
+	Disconnected,
+}
+
+#[derive(Clone, Copy, Default)]
+struct MockMotorState {
+	position: Angle,
+	velocity: AngularVelocity,
+	voltage: ElectricPotential,
+	disconnected: bool,
+}
+
+/// In-memory motor for running drive code off the robot
+///
+/// Clones share the same state, so a test or simulation can keep a clone to read back the commanded voltage and to
+/// feed in positions and velocities while a drive train owns the other.
+#[derive(Clone, Default)]
+pub struct MockMotor {
+	state: Rc<Cell<MockMotorState>>,
+}
+
+impl MockMotor {
+	/// Creates a stationary motor at its zero position
+	pub fn new() -> Self { Self::default() }
+
+	/// Gets the voltage most recently commanded, with ratio commands scaled to [`MAX_VOLTAGE`]
+	pub fn voltage(&self) -> ElectricPotential { self.state.get().voltage }
+
+	/// Sets the position reported by the motor
+	pub fn set_position(&self, position: Angle) { self.update(|state| state.position = position); }
+
+	/// Turns the motor shaft by a relative angle
+	pub fn rotate(&self, angle: Angle) { self.update(|state| state.position += angle); }
+
+	/// Sets the velocity reported by the motor
+	pub fn set_actual_velocity(&self, velocity: AngularVelocity) { self.update(|state| state.velocity = velocity); }
+
+	/// Marks the motor as unplugged, causing every call through [`SmartMotor`] to fail
+	pub fn set_disconnected(&self, disconnected: bool) { self.update(|state| state.disconnected = disconnected); }
+
+	fn update(&self, f: impl FnOnce(&mut MockMotorState)) {
+		let mut state: MockMotorState = self.state.get();
+		f(&mut state);
+		self.state.set(state);
+	}
+
+	fn connected_state(&self) -> Result<MockMotorState, MockMotorError> {
+		let state: MockMotorState = self.state.get();
+		if state.disconnected {
+			Err(MockMotorError::Disconnected)
+		} else {
+			Ok(state)
+		}
+	}
+}
+
+impl SmartMotor for MockMotor {
+	type Error = MockMotorError;
+
+	fn move_ratio(&mut self, value: Ratio) -> Result<(), Self::Error> { self.move_voltage(value * MAX_VOLTAGE) }
+
+	fn move_voltage(&mut self, voltage: ElectricPotential) -> Result<(), Self::Error> {
+		self.connected_state()?;
+		self.update(|state| state.voltage = voltage.max(-MAX_VOLTAGE).min(MAX_VOLTAGE));
+		Ok(())
+	}
+
+	fn tare_position(&mut self) -> Result<(), Self::Error> {
+		self.connected_state()?;
+		self.update(|state| state.position = Angle::ZERO);
+		Ok(())
+	}
+
+	fn get_position(&self) -> Result<Angle, Self::Error> { Ok(self.connected_state()?.position) }
+
+	fn get_actual_velocity(&self) -> Result<AngularVelocity, Self::Error> { Ok(self.connected_state()?.velocity) }
+}
@@ -0,0 +1,354 @@
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+use libm::{copysign, fabs};
+use uom::{
+	si::{
+		angle::{radian, revolution},
+		f64::{Angle, Length, Ratio},
+		length::inch,
+		ratio::ratio,
+	},
+	ConstZero,
+};
+#[cfg(feature = "vex-rt")]
+use vex_rt::{
+	prelude::println,
+	rtos::{Context, Loop},
+	select,
+};
+
+use super::{TrackingLayout, WheelOrientation};
+use crate::encoder::Encoder;
+#[cfg(feature = "vex-rt")]
+use crate::{inertial::InertialSensor, motor::SmartMotor, tank_drive::TankDrive, PID_CYCLE_DURATION};
+
+/// Smallest rotation, in radians, a wheel has to make during a calibration run for its measurement to be trusted
+const MIN_WHEEL_ROTATION: f64 = 0.1;
+
+/// Smallest rotation, in radians, the robot has to make during the spins for the offsets to be solved from them
+const MIN_SPIN_ROTATION: f64 = 0.5;
+
+/// Number of cycles the robot is given to come to a stop after a run the calibrator drives itself, before the wheels
+/// are read
+#[cfg(feature = "vex-rt")]
+const STOPPING_CYCLES: usize = 10;
+
+/// Error returned when a [`Calibrator`] cannot solve for the layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CalibrationError {
+	/// The straight drive has not been recorded
+	StraightNotRecorded,
+	/// The spins have not been recorded
+	SpinsNotRecorded,
+	/// The robot barely turned during the spins, so the offsets cannot be solved from them
+	SpinsTooSmall,
+	/// The wheel at this index in the layout barely turned during a run it should have measured
+	WheelDidNotTurn(usize),
+	/// The wheel at this index in the layout turned backwards during a run, so its encoder needs reversing
+	WheelReversed(usize),
+}
+
+/// Error returned when a device fails while a [`Calibrator`] drives a run itself
+#[cfg(feature = "vex-rt")]
+#[derive(Debug)]
+pub enum CalibrationRunError<E, M, I> {
+	/// A tracking wheel encoder failed
+	Encoder(E),
+	/// A drive motor failed
+	Motor(M),
+	/// The inertial sensor failed
+	Inertial(I),
+}
+
+/// Measured geometry of one tracking wheel
+#[derive(Clone, Copy, Debug)]
+pub struct WheelCalibration {
+	/// Direction the wheel rolls in, as given in the layout
+	pub orientation: WheelOrientation,
+	/// Effective diameter of the wheel, which takes in how far it sinks into the tiles
+	pub diameter: Length,
+	/// Signed offset of the wheel from the robot's centre of rotation, to the right for parallel wheels and in front
+	/// for perpendicular wheels
+	pub offset: Length,
+	/// Gear ratio between the wheel and its encoder, as given in the layout
+	pub gear_ratio: Ratio,
+	/// Noise of the wheel, as given in the layout
+	pub noise: Ratio,
+}
+
+/// Geometry of every wheel in a layout, in the same order as the layout
+///
+/// Displays as the code to build the calibrated layout, ready to be pasted over the layout measured by hand, with
+/// `encoder_0`, `encoder_1` and so on standing in for the encoders.
+#[derive(Clone, Debug)]
+pub struct Calibration {
+	/// Geometry of each wheel
+	pub wheels: Vec<WheelCalibration>,
+}
+
+impl Calibration {
+	/// Updates the diameter and offset of every wheel in a layout, which has to be the layout it was measured from
+	pub fn apply<E>(&self, layout: &mut TrackingLayout<E>) {
+		for (wheel, calibration) in layout.wheels.iter_mut().zip(self.wheels.iter()) {
+			wheel.wheel_radius = calibration.diameter / 2.0;
+			wheel.offset = calibration.offset;
+		}
+	}
+
+	/// Prints the code to build the calibrated layout to the terminal
+	#[cfg(feature = "vex-rt")]
+	pub fn print(&self) {
+		println!("{}", self);
+	}
+}
+
+impl Display for Calibration {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		writeln!(f, "TrackingLayout::new(vec![")?;
+
+		for (index, wheel) in self.wheels.iter().enumerate() {
+			let constructor: &str = match wheel.orientation {
+				WheelOrientation::Parallel => "parallel",
+				WheelOrientation::Perpendicular => "perpendicular",
+			};
+
+			write!(
+				f,
+				"\tTrackingWheel::{}(encoder_{}, Length::new::<inch>({:.4}), Length::new::<inch>({:.4}))",
+				constructor,
+				index,
+				wheel.diameter.get::<inch>(),
+				wheel.offset.get::<inch>(),
+			)?;
+
+			if wheel.gear_ratio != Ratio::new::<ratio>(1.0) {
+				write!(
+					f,
+					"\n\t\t.with_gear_ratio(Ratio::new::<ratio>({}))",
+					wheel.gear_ratio.get::<ratio>()
+				)?;
+			}
+
+			if wheel.noise != Ratio::new::<ratio>(0.02) {
+				write!(
+					f,
+					"\n\t\t.with_noise(Ratio::new::<ratio>({}))",
+					wheel.noise.get::<ratio>()
+				)?;
+			}
+
+			writeln!(f, ",")?;
+		}
+
+		write!(f, "])")
+	}
+}
+
+/// Measures the effective diameter and offset of each tracking wheel from runs over known distances and rotations
+///
+/// Each run is started with [`Calibrator::start`] and finished once the robot has stopped:
+///
+/// 1. Drive straight forwards over a known distance, such as along a wall from one tile seam to another, and finish
+///    with [`Calibrator::finish_straight`]. This gives the diameter of the parallel wheels.
+/// 2. Optionally strafe sideways over a known distance and finish with [`Calibrator::finish_strafe`]. This gives the
+///    diameter of the perpendicular wheels, which otherwise are taken to be off by as much as the parallel wheels.
+/// 3. Spin on the spot a known number of times, such as until the robot lines up with a wall again, and finish with
+///    [`Calibrator::finish_spins`]. This gives the offsets, measured from the point the robot spun around, so that
+///    point becomes the tracking centre. With an inertial sensor, [`Calibrator::run_spins`] drives this run itself.
+///
+/// More distance and more spins average out the slop in each run, and driving slowly keeps the wheels from slipping.
+pub struct Calibrator<E> {
+	layout: TrackingLayout<E>,
+	start: Vec<Angle>,
+
+	straight: Option<(Length, Vec<Angle>)>,
+	strafe: Option<(Length, Vec<Angle>)>,
+	spins: Option<(Angle, Vec<Angle>)>,
+}
+
+impl<E> Calibrator<E> {
+	/// Creates a calibrator for the wheels of a layout, using their current diameters as a starting point for any
+	/// wheel without a run of its own
+	pub fn new(layout: TrackingLayout<E>) -> Self {
+		Self {
+			layout,
+			start: Vec::new(),
+			straight: None,
+			strafe: None,
+			spins: None,
+		}
+	}
+
+	/// Gives back the layout, without applying the calibration
+	pub fn into_layout(self) -> TrackingLayout<E> { self.layout }
+
+	/// Solves for the geometry of every wheel from the runs recorded so far
+	pub fn solve(&self) -> Result<Calibration, CalibrationError> {
+		let (distance, straight) = self.straight.as_ref().ok_or(CalibrationError::StraightNotRecorded)?;
+		let (rotation, spins) = self.spins.as_ref().ok_or(CalibrationError::SpinsNotRecorded)?;
+
+		if fabs(rotation.get::<radian>()) < MIN_SPIN_ROTATION {
+			return Err(CalibrationError::SpinsTooSmall);
+		}
+
+		let mut diameters: Vec<Option<Length>> = Vec::with_capacity(self.layout.wheels.len());
+
+		for (index, wheel) in self.layout.wheels.iter().enumerate() {
+			let run: Option<(Length, &Vec<Angle>)> = match (wheel.orientation, &self.strafe) {
+				(WheelOrientation::Parallel, _) => Some((*distance, straight)),
+				(WheelOrientation::Perpendicular, Some((distance, strafe))) => Some((*distance, strafe)),
+				(WheelOrientation::Perpendicular, None) => None,
+			};
+
+			diameters.push(match run {
+				Some((distance, rotations)) => Some(diameter(index, distance, rotations[index])?),
+				None => None,
+			});
+		}
+
+		// Wheels without a run of their own are scaled by the average error in the diameters which were measured
+		let measured: Vec<Ratio> = self
+			.layout
+			.wheels
+			.iter()
+			.zip(diameters.iter())
+			.filter_map(|(wheel, diameter)| diameter.map(|diameter| diameter / (wheel.wheel_radius * 2.0)))
+			.collect();
+		let scale: Ratio = if measured.is_empty() {
+			Ratio::new::<ratio>(1.0)
+		} else {
+			measured.iter().fold(Ratio::ZERO, |sum, scale| sum + *scale) / measured.len() as f64
+		};
+
+		let wheels: Vec<WheelCalibration> = self
+			.layout
+			.wheels
+			.iter()
+			.zip(diameters)
+			.enumerate()
+			.map(|(index, (wheel, diameter))| {
+				let diameter: Length = diameter.unwrap_or(wheel.wheel_radius * 2.0 * scale);
+				let rolled: Length = spins[index] * diameter / 2.0;
+
+				let offset: Length = match wheel.orientation {
+					WheelOrientation::Parallel => -rolled / rotation.get::<radian>(),
+					WheelOrientation::Perpendicular => rolled / rotation.get::<radian>(),
+				};
+
+				WheelCalibration {
+					orientation: wheel.orientation,
+					diameter,
+					offset,
+					gear_ratio: wheel.gear_ratio,
+					noise: wheel.noise,
+				}
+			})
+			.collect();
+
+		Ok(Calibration { wheels })
+	}
+}
+
+impl<E: Encoder> Calibrator<E> {
+	/// Starts a run from where the robot is now
+	pub fn start(&mut self) -> Result<(), E::Error> {
+		self.start = self.rotations()?;
+		Ok(())
+	}
+
+	/// Finishes a run in which the robot drove straight forwards over a known distance
+	pub fn finish_straight(&mut self, distance: Length) -> Result<(), E::Error> {
+		self.straight = Some((distance, self.finish()?));
+		Ok(())
+	}
+
+	/// Finishes a run in which the robot strafed right over a known distance
+	pub fn finish_strafe(&mut self, distance: Length) -> Result<(), E::Error> {
+		self.strafe = Some((distance, self.finish()?));
+		Ok(())
+	}
+
+	/// Finishes a run in which the robot spun on the spot a known number of times, clockwise when positive
+	pub fn finish_spins(&mut self, spins: f64) -> Result<(), E::Error> {
+		self.spins = Some((Angle::new::<revolution>(spins), self.finish()?));
+		Ok(())
+	}
+
+	/// Rotation of each wheel since the run was started, or since the encoders were zeroed if it never was
+	fn finish(&self) -> Result<Vec<Angle>, E::Error> {
+		let mut rotations: Vec<Angle> = self.rotations()?;
+
+		for (index, rotation) in rotations.iter_mut().enumerate() {
+			*rotation -= self.start.get(index).copied().unwrap_or(Angle::ZERO);
+		}
+
+		Ok(rotations)
+	}
+
+	fn rotations(&self) -> Result<Vec<Angle>, E::Error> {
+		self.layout.wheels.iter().map(|wheel| wheel.rotation()).collect()
+	}
+}
+
+#[cfg(feature = "vex-rt")]
+impl<E: Encoder> Calibrator<E> {
+	/// Spins a tank drive on the spot at a power until an inertial sensor has turned by a number of spins, clockwise
+	/// when positive, and finishes the spins with the rotation the sensor measured once the robot has stopped
+	///
+	/// The run is started here, and the sensor stands in for a wall to line up against, so the count only has to be
+	/// roughly right. Returns whether the run was recorded, which it is not if the context is cancelled first.
+	pub fn run_spins<M: SmartMotor, I: InertialSensor, const N: usize>(
+		&mut self, drive: &mut TankDrive<M, N>, inertial: &I, spins: f64, power: Ratio, ctx: &Context,
+	) -> Result<bool, CalibrationRunError<E::Error, M::Error, I::Error>> {
+		let target: f64 = fabs(Angle::new::<revolution>(spins).get::<radian>());
+		let power: Ratio = Ratio::new::<ratio>(copysign(power.get::<ratio>(), spins));
+
+		self.start().map_err(CalibrationRunError::Encoder)?;
+		let start: Angle = inertial.get_rotation().map_err(CalibrationRunError::Inertial)?;
+
+		let mut stopping: usize = 0;
+		let mut pause = Loop::new(PID_CYCLE_DURATION);
+
+		loop {
+			let rotation: Angle = inertial.get_rotation().map_err(CalibrationRunError::Inertial)? - start;
+
+			if stopping > 0 || fabs(rotation.get::<radian>()) >= target {
+				drive
+					.drive_tank(Ratio::ZERO, Ratio::ZERO)
+					.map_err(CalibrationRunError::Motor)?;
+				stopping += 1;
+			} else {
+				drive.drive_tank(power, -power).map_err(CalibrationRunError::Motor)?;
+			}
+
+			if stopping > STOPPING_CYCLES {
+				self.spins = Some((rotation, self.finish().map_err(CalibrationRunError::Encoder)?));
+				return Ok(true);
+			}
+
+			select! {
+				_ = ctx.done() => break,
+				_ = pause.select() => continue
+			}
+		}
+
+		drive
+			.drive_tank(Ratio::ZERO, Ratio::ZERO)
+			.map_err(CalibrationRunError::Motor)?;
+		Ok(false)
+	}
+}
+
+/// Diameter of a wheel which turned by a given rotation while rolling over a given distance
+fn diameter(index: usize, distance: Length, rotation: Angle) -> Result<Length, CalibrationError> {
+	let rotation: f64 = rotation.get::<radian>() * copysign(1.0, distance.get::<inch>());
+
+	if fabs(rotation) < MIN_WHEEL_ROTATION {
+		Err(CalibrationError::WheelDidNotTurn(index))
+	} else if rotation < 0.0 {
+		Err(CalibrationError::WheelReversed(index))
+	} else {
+		Ok(distance.abs() * 2.0 / rotation)
+	}
+}
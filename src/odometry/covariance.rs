@@ -0,0 +1,89 @@
+use libm::{cos, sin, sqrt};
+use uom::si::{
+	angle::radian,
+	f64::{Angle, Length},
+	length::meter,
+};
+
+use super::layout::LocalMovement;
+
+type Matrix = [[f64; 3]; 3];
+
+/// Uncertainty in a tracked position, as the covariance of its x coordinate, y coordinate and heading
+///
+/// The matrix is in metres and radians, in that order, so the heading terms mix units and cannot be held in typed
+/// quantities. The accessors give the standard deviations that are usually wanted.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PoseCovariance(pub [[f64; 3]; 3]);
+
+impl PoseCovariance {
+	/// Standard deviation of the x coordinate
+	pub fn x_deviation(&self) -> Length { Length::new::<meter>(sqrt(self.0[0][0].max(0.0))) }
+
+	/// Standard deviation of the y coordinate
+	pub fn y_deviation(&self) -> Length { Length::new::<meter>(sqrt(self.0[1][1].max(0.0))) }
+
+	/// Standard deviation of the heading
+	pub fn heading_deviation(&self) -> Angle { Angle::new::<radian>(sqrt(self.0[2][2].max(0.0))) }
+
+	/// Root mean square distance between the tracked and true coordinates, whatever direction the error is in
+	pub fn position_deviation(&self) -> Length { Length::new::<meter>(sqrt((self.0[0][0] + self.0[1][1]).max(0.0))) }
+
+	/// Grows the covariance by a robot-relative movement made about an average heading, given the covariance of the
+	/// sideways movement, forward movement and rotation
+	pub(super) fn propagate(&mut self, average_heading: Angle, movement: LocalMovement, movement_covariance: Matrix) {
+		let heading: f64 = average_heading.get::<radian>();
+		let sideways: f64 = movement.sideways.get::<meter>();
+		let forward: f64 = movement.forward.get::<meter>();
+
+		// Change in the field movement for a change in heading
+		let x_by_heading: f64 = forward * cos(heading) - sideways * sin(heading);
+		let y_by_heading: f64 = -forward * sin(heading) - sideways * cos(heading);
+
+		let state_jacobian: Matrix = [[1.0, 0.0, x_by_heading], [0.0, 1.0, y_by_heading], [0.0, 0.0, 1.0]];
+
+		// The movement is carried onto the field at the heading halfway through the rotation, hence the halves
+		let movement_jacobian: Matrix = [
+			[cos(heading), sin(heading), x_by_heading / 2.0],
+			[-sin(heading), cos(heading), y_by_heading / 2.0],
+			[0.0, 0.0, 1.0],
+		];
+
+		let carried: Matrix = transform(state_jacobian, self.0);
+		let added: Matrix = transform(movement_jacobian, movement_covariance);
+
+		for ((row, carried_row), added_row) in self.0.iter_mut().zip(carried).zip(added) {
+			for ((value, carried), added) in row.iter_mut().zip(carried_row).zip(added_row) {
+				*value = carried + added;
+			}
+		}
+	}
+
+	/// Replaces the uncertainty in one of the x, y or heading terms after it has been set to a measured value, which
+	/// is uncertain by its own variance and unrelated to the other terms
+	pub(super) fn reset(&mut self, index: usize, variance: f64) {
+		for other in 0..3 {
+			self.0[index][other] = 0.0;
+			self.0[other][index] = 0.0;
+		}
+
+		self.0[index][index] = variance;
+	}
+}
+
+/// Carries a covariance through a linear transformation, as J Σ Jᵀ
+fn transform(jacobian: Matrix, covariance: Matrix) -> Matrix {
+	let mut result: Matrix = [[0.0; 3]; 3];
+
+	for (i, row) in result.iter_mut().enumerate() {
+		for (j, value) in row.iter_mut().enumerate() {
+			for (jacobian_ik, covariance_row) in jacobian[i].iter().zip(covariance.iter()) {
+				for (covariance_kl, jacobian_jl) in covariance_row.iter().zip(jacobian[j].iter()) {
+					*value += jacobian_ik * covariance_kl * jacobian_jl;
+				}
+			}
+		}
+	}
+
+	result
+}
@@ -0,0 +1,75 @@
+use uom::si::{
+	angle::radian,
+	f64::{Angle, Ratio},
+	ratio::ratio,
+};
+
+/// Strategy for combining the heading measured by the tracking wheels with the heading from an inertial sensor
+#[derive(Clone, Copy, Debug)]
+pub enum HeadingFusion {
+	/// Ignore the tracking wheels and take the heading straight from the inertial sensor
+	Replace,
+	/// Follow the tracking wheels from cycle to cycle while pulling the heading towards the inertial sensor by a fixed
+	/// share each cycle
+	Complementary {
+		/// Share of the inertial heading mixed in each cycle, between 0 and 1
+		inertial_weight: Ratio,
+	},
+	/// Weight the two sources by how uncertain each one currently is, with a one dimensional Kalman filter
+	Kalman {
+		/// Standard deviation of the tracking wheel error, as a ratio of the rotation they measure
+		wheel_noise: Ratio,
+		/// Standard deviation of the inertial sensor's heading
+		inertial_noise: Angle,
+	},
+}
+
+/// Running state of a [`HeadingFusion`] strategy
+pub(super) struct HeadingFilter {
+	fusion: HeadingFusion,
+	/// Variance of the fused heading in square radians, only tracked by the Kalman filter
+	variance: f64,
+}
+
+impl HeadingFilter {
+	pub(super) fn new(fusion: HeadingFusion) -> Self { Self { fusion, variance: 0.0 } }
+
+	/// Combines the heading reached by applying the wheels' heading change with the inertial sensor's heading,
+	/// falling back on the sensor alone when the wheels cannot measure rotation
+	pub(super) fn fuse(&mut self, heading: Angle, wheel_change: Option<Angle>, inertial_heading: Angle) -> Angle {
+		let wheel_change: Angle = match wheel_change {
+			Some(wheel_change) => wheel_change,
+			None => return inertial_heading,
+		};
+		let wheel_heading: Angle = heading + wheel_change;
+
+		match self.fusion {
+			HeadingFusion::Replace => inertial_heading,
+			HeadingFusion::Complementary { inertial_weight } => {
+				let weight: f64 = inertial_weight.get::<ratio>().clamp(0.0, 1.0);
+				wheel_heading * (1.0 - weight) + inertial_heading * weight
+			},
+			HeadingFusion::Kalman {
+				wheel_noise,
+				inertial_noise,
+			} => {
+				let process_deviation: f64 = wheel_noise.get::<ratio>() * wheel_change.get::<radian>();
+				let process_variance: f64 = process_deviation * process_deviation;
+				let measurement_variance: f64 = inertial_noise.get::<radian>() * inertial_noise.get::<radian>();
+
+				let predicted_variance: f64 = self.variance + process_variance;
+				let total_variance: f64 = predicted_variance + measurement_variance;
+
+				let gain: f64 = if total_variance > 0.0 {
+					predicted_variance / total_variance
+				} else {
+					1.0
+				};
+
+				self.variance = (1.0 - gain) * predicted_variance;
+
+				wheel_heading + (inertial_heading - wheel_heading) * gain
+			},
+		}
+	}
+}
@@ -0,0 +1,97 @@
+use libm::fabs;
+use uom::{
+	si::{
+		angle::radian,
+		f64::{Angle, Length, Ratio},
+		ratio::ratio,
+	},
+	ConstZero,
+};
+
+use super::layout::LocalMovement;
+use crate::math::RealAngle;
+
+/// Rotations smaller than this, in radians, use a Taylor series for the arc's chord rather than dividing by the
+/// rotation
+const SMALL_ANGLE: f64 = 1e-3;
+
+/// Scheme used to turn the robot-relative movement measured each cycle into a change in field position
+///
+/// Each cycle the robot is taken to have moved along a constant curvature arc, turning steadily while it drives.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Integration {
+	/// Integrates the arc exactly, moving along its chord at the heading halfway through the turn
+	#[default]
+	ExactArc,
+	/// Moves the full measured distance in a straight line at the heading halfway through the turn, which is accurate
+	/// to second order in the rotation
+	Midpoint,
+	/// Integrates the arc with the classic fourth order Runge-Kutta method, splitting the cycle into a number of
+	/// equal substeps
+	RungeKutta {
+		/// Number of substeps per cycle, at least one
+		substeps: u8,
+	},
+}
+
+impl Integration {
+	/// Change in field x and y coordinates from a robot-relative movement starting at a given heading
+	pub(super) fn field_change(self, heading: Angle, movement: LocalMovement) -> (Length, Length) {
+		let rotation: Angle = movement.rotation;
+
+		match self {
+			Integration::ExactArc => {
+				let chord: Ratio = chord_ratio(rotation);
+				rotate(
+					movement.sideways * chord,
+					movement.forward * chord,
+					heading + rotation / 2.0,
+				)
+			},
+			Integration::Midpoint => rotate(movement.sideways, movement.forward, heading + rotation / 2.0),
+			Integration::RungeKutta { substeps } => {
+				let substeps: f64 = f64::from(substeps.max(1));
+				let sideways: Length = movement.sideways / substeps;
+				let forward: Length = movement.forward / substeps;
+				let step_rotation: Angle = rotation / substeps;
+
+				let mut x: Length = Length::ZERO;
+				let mut y: Length = Length::ZERO;
+				let mut step_heading: Angle = heading;
+
+				for _ in 0..substeps as usize {
+					// The heading does not depend on the position, so the two middle stages are the same
+					let (start_x, start_y) = rotate(sideways, forward, step_heading);
+					let (middle_x, middle_y) = rotate(sideways, forward, step_heading + step_rotation / 2.0);
+					let (end_x, end_y) = rotate(sideways, forward, step_heading + step_rotation);
+
+					x += (start_x + middle_x * 4.0 + end_x) / 6.0;
+					y += (start_y + middle_y * 4.0 + end_y) / 6.0;
+					step_heading += step_rotation;
+				}
+
+				(x, y)
+			},
+		}
+	}
+}
+
+/// Ratio of the chord of an arc to its length, 2 sin(θ/2) / θ
+fn chord_ratio(rotation: Angle) -> Ratio {
+	let angle: f64 = rotation.get::<radian>();
+
+	if fabs(angle) < SMALL_ANGLE {
+		let square: f64 = angle * angle;
+		Ratio::new::<ratio>(1.0 - square / 24.0 + square * square / 1920.0)
+	} else {
+		2.0 * (rotation / 2.0).sin() / Ratio::new::<ratio>(angle)
+	}
+}
+
+/// Carries a sideways and forward movement onto the field axes at a given heading
+fn rotate(sideways: Length, forward: Length, heading: Angle) -> (Length, Length) {
+	let sin: Ratio = heading.sin();
+	let cos: Ratio = heading.cos();
+
+	(forward * sin + sideways * cos, forward * cos - sideways * sin)
+}
@@ -0,0 +1,297 @@
+use alloc::{vec, vec::Vec};
+
+use libm::fabs;
+use uom::{
+	si::{
+		angle::radian,
+		f64::{Angle, Length, Ratio},
+		length::meter,
+		ratio::ratio,
+	},
+	ConstZero,
+};
+
+use crate::{encoder::Encoder, math::least_squares};
+
+/// Direction a tracking wheel rolls in relative to the robot
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WheelOrientation {
+	/// Rolls as the robot drives forwards
+	Parallel,
+	/// Rolls as the robot strafes sideways
+	Perpendicular,
+}
+
+/// Error returned when a [`TrackingLayout`] cannot be used to follow the robot
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+	/// No wheel rolls forwards, so forward movement cannot be measured
+	NoParallelWheel,
+	/// The wheels cannot tell turning apart from driving and no inertial sensor was given to measure the heading
+	HeadingUnobservable,
+}
+
+/// Wheel whose rotation is measured to follow the robot, along with where and how it is mounted
+pub struct TrackingWheel<E> {
+	encoder: E,
+	pub(super) wheel_radius: Length,
+	pub(super) offset: Length,
+	pub(super) orientation: WheelOrientation,
+	pub(super) gear_ratio: Ratio,
+	pub(super) noise: Ratio,
+
+	last_position: Angle,
+	distance_change: Length,
+}
+
+impl<E> TrackingWheel<E> {
+	/// Creates a wheel which rolls forwards, mounted a signed distance to the right of the tracking centre
+	pub fn parallel(encoder: E, wheel_diameter: Length, offset: Length) -> Self {
+		Self::new(encoder, wheel_diameter, offset, WheelOrientation::Parallel)
+	}
+
+	/// Creates a wheel which rolls sideways, mounted a signed distance in front of the tracking centre
+	pub fn perpendicular(encoder: E, wheel_diameter: Length, offset: Length) -> Self {
+		Self::new(encoder, wheel_diameter, offset, WheelOrientation::Perpendicular)
+	}
+
+	/// Sets the ratio of wheel speed to encoder speed, for encoders which are geared to their wheel
+	pub fn with_gear_ratio(mut self, gear_ratio: Ratio) -> Self {
+		self.gear_ratio = gear_ratio;
+		self
+	}
+
+	/// Sets the standard deviation of the error in the distance the wheel measures, as a ratio of the distance it
+	/// rolls, which defaults to 2%
+	pub fn with_noise(mut self, noise: Ratio) -> Self {
+		self.noise = noise;
+		self
+	}
+
+	fn new(encoder: E, wheel_diameter: Length, offset: Length, orientation: WheelOrientation) -> Self {
+		Self {
+			encoder,
+			wheel_radius: wheel_diameter / 2.0,
+			offset,
+			orientation,
+			gear_ratio: Ratio::new::<ratio>(1.0),
+			noise: Ratio::new::<ratio>(0.02),
+			last_position: Angle::ZERO,
+			distance_change: Length::ZERO,
+		}
+	}
+
+	/// Coefficients relating the robot's sideways movement, forward movement and clockwise rotation to the distance
+	/// the wheel rolls, in metres and radians
+	fn coefficients(&self) -> [f64; 3] {
+		let offset: f64 = self.offset.get::<meter>();
+
+		match self.orientation {
+			WheelOrientation::Parallel => [0.0, 1.0, -offset],
+			WheelOrientation::Perpendicular => [1.0, 0.0, offset],
+		}
+	}
+
+	/// Records a new reading of the encoder, working out how far the wheel has rolled since the last one
+	fn record(&mut self, position: Angle) {
+		self.distance_change = (position - self.last_position) * self.gear_ratio * self.wheel_radius;
+		self.last_position = position;
+	}
+}
+
+impl<E: Encoder> TrackingWheel<E> {
+	fn reset(&mut self) -> Result<(), E::Error> {
+		self.encoder.set_position(Angle::ZERO)?;
+		self.last_position = Angle::ZERO;
+		self.distance_change = Length::ZERO;
+		Ok(())
+	}
+
+	/// Gets the absolute rotation of the wheel itself, through the gearing to its encoder
+	pub(super) fn rotation(&self) -> Result<Angle, E::Error> {
+		let rotation: Angle = (self.encoder.get_position()? * self.gear_ratio).into();
+		Ok(rotation)
+	}
+}
+
+/// Robot-relative movement over one odometry cycle
+#[derive(Clone, Copy, Default)]
+pub(super) struct LocalMovement {
+	pub(super) sideways: Length,
+	pub(super) forward: Length,
+	pub(super) rotation: Angle,
+}
+
+/// Set of tracking wheels mounted on a robot
+///
+/// Any number of wheels can be combined, and the movement they measure is solved for by least squares, so redundant
+/// wheels average out each other's error. At least one wheel has to roll forwards. Without a perpendicular wheel the
+/// robot is assumed not to slide sideways, and unless there are two parallel or two perpendicular wheels at different
+/// offsets the heading has to come from an inertial sensor.
+pub struct TrackingLayout<E> {
+	pub(super) wheels: Vec<TrackingWheel<E>>,
+}
+
+impl<E> TrackingLayout<E> {
+	/// Creates a layout from any combination of wheels
+	pub fn new(wheels: Vec<TrackingWheel<E>>) -> Self { Self { wheels } }
+
+	/// Two parallel wheels on either side of the tracking centre and one perpendicular wheel behind it, all the
+	/// same size, with offsets given as positive distances from the tracking centre
+	pub fn three_wheel(
+		left_encoder: E, right_encoder: E, rear_encoder: E, wheel_diameter: Length, left_offset: Length,
+		right_offset: Length, rear_offset: Length,
+	) -> Self {
+		Self::new(vec![
+			TrackingWheel::parallel(left_encoder, wheel_diameter, -left_offset),
+			TrackingWheel::parallel(right_encoder, wheel_diameter, right_offset),
+			TrackingWheel::perpendicular(rear_encoder, wheel_diameter, -rear_offset),
+		])
+	}
+
+	/// Two parallel wheels on either side of the tracking centre, with offsets given as positive distances from the
+	/// tracking centre
+	pub fn two_wheel(
+		left_encoder: E, right_encoder: E, wheel_diameter: Length, left_offset: Length, right_offset: Length,
+	) -> Self {
+		Self::new(vec![
+			TrackingWheel::parallel(left_encoder, wheel_diameter, -left_offset),
+			TrackingWheel::parallel(right_encoder, wheel_diameter, right_offset),
+		])
+	}
+
+	/// One parallel and one perpendicular wheel, which needs an inertial sensor for the heading
+	pub fn parallel_perpendicular(parallel: TrackingWheel<E>, perpendicular: TrackingWheel<E>) -> Self {
+		Self::new(vec![parallel, perpendicular])
+	}
+
+	/// The encoders built into the drive motors of a tank drive, such as
+	/// [`MotorEncoder`](crate::encoder::MotorEncoder)s, with the same geometry as
+	/// [`TankDrive`](crate::tank_drive::TankDrive)
+	pub fn drive_encoders(
+		left_encoder: E, right_encoder: E, wheel_diameter: Length, drive_ratio: Ratio, track_width: Length,
+	) -> Self {
+		Self::new(vec![
+			TrackingWheel::parallel(left_encoder, wheel_diameter, -track_width / 2.0).with_gear_ratio(drive_ratio),
+			TrackingWheel::parallel(right_encoder, wheel_diameter, track_width / 2.0).with_gear_ratio(drive_ratio),
+		])
+	}
+
+	/// Whether the wheels measure sideways movement rather than assuming there is none
+	fn measures_sideways(&self) -> bool {
+		self.wheels
+			.iter()
+			.any(|wheel| wheel.orientation == WheelOrientation::Perpendicular)
+	}
+
+	/// Whether the wheels can measure rotation on their own
+	pub(super) fn measures_rotation(&self) -> bool { self.solve(None).is_some() }
+
+	/// Checks the layout can follow the robot, with or without an inertial sensor to supply the heading
+	pub(super) fn validate(&self, has_inertial: bool) -> Result<(), LayoutError> {
+		if !self
+			.wheels
+			.iter()
+			.any(|wheel| wheel.orientation == WheelOrientation::Parallel)
+		{
+			Err(LayoutError::NoParallelWheel)
+		} else if !has_inertial && !self.measures_rotation() {
+			Err(LayoutError::HeadingUnobservable)
+		} else {
+			Ok(())
+		}
+	}
+
+	/// Solves for the movement measured by the last read, optionally with the rotation already known
+	pub(super) fn solve(&self, rotation: Option<Angle>) -> Option<LocalMovement> {
+		let [sideways, forward, turn]: [f64; 3] = self.solve_distances(
+			|wheel: &TrackingWheel<E>, _| wheel.distance_change.get::<meter>(),
+			rotation.map(|rotation: Angle| rotation.get::<radian>()),
+		)?;
+
+		Some(LocalMovement {
+			sideways: Length::new::<meter>(sideways),
+			forward: Length::new::<meter>(forward),
+			rotation: Angle::new::<radian>(turn),
+		})
+	}
+
+	/// Covariance of the movement measured by the last read, in metres and radians, from the noise of each wheel
+	///
+	/// The solution is linear in the distances rolled, so each wheel's error carries through as the movement solved
+	/// for when only that wheel rolls by its standard deviation. A known rotation is taken to be exact.
+	pub(super) fn movement_covariance(&self, rotation_known: bool) -> [[f64; 3]; 3] {
+		let mut covariance: [[f64; 3]; 3] = [[0.0; 3]; 3];
+
+		for index in 0..self.wheels.len() {
+			let deviation = |wheel: &TrackingWheel<E>, wheel_index: usize| {
+				if wheel_index == index {
+					wheel.noise.get::<ratio>() * fabs(wheel.distance_change.get::<meter>())
+				} else {
+					0.0
+				}
+			};
+
+			if let Some(error) = self.solve_distances(deviation, rotation_known.then_some(0.0)) {
+				for (row, row_error) in covariance.iter_mut().zip(error) {
+					for (value, column_error) in row.iter_mut().zip(error) {
+						*value += row_error * column_error;
+					}
+				}
+			}
+		}
+
+		covariance
+	}
+
+	/// Solves for the sideways movement, forward movement and rotation, in metres and radians, given the distance
+	/// each wheel rolled
+	fn solve_distances(
+		&self, distance: impl Fn(&TrackingWheel<E>, usize) -> f64, rotation: Option<f64>,
+	) -> Option<[f64; 3]> {
+		let rows = self.wheels.iter().enumerate().map(|(index, wheel)| {
+			let [sideways, forward, turn]: [f64; 3] = wheel.coefficients();
+			let known: f64 = rotation.map_or(0.0, |rotation| turn * rotation);
+
+			(sideways, forward, turn, distance(wheel, index) - known)
+		});
+
+		Some(match (self.measures_sideways(), rotation) {
+			(true, Some(rotation)) => {
+				let [sideways, forward] = least_squares(rows.map(|(s, f, _, d)| ([s, f], d)))?;
+				[sideways, forward, rotation]
+			},
+			(true, None) => least_squares(rows.map(|(s, f, t, d)| ([s, f, t], d)))?,
+			(false, Some(rotation)) => {
+				let [forward] = least_squares(rows.map(|(_, f, _, d)| ([f], d)))?;
+				[0.0, forward, rotation]
+			},
+			(false, None) => {
+				let [forward, turn] = least_squares(rows.map(|(_, f, t, d)| ([f, t], d)))?;
+				[0.0, forward, turn]
+			},
+		})
+	}
+}
+
+impl<E: Encoder> TrackingLayout<E> {
+	pub(super) fn reset(&mut self) -> Result<(), E::Error> { self.wheels.iter_mut().try_for_each(TrackingWheel::reset) }
+
+	/// Reads every encoder, recording how far each wheel has rolled since the last read
+	///
+	/// Nothing is recorded unless every encoder can be read, so a failed read leaves the movement to be picked up by
+	/// the next one rather than losing it from the wheels which were read.
+	pub(super) fn read(&mut self) -> Result<(), E::Error> {
+		let positions: Vec<Angle> = self
+			.wheels
+			.iter()
+			.map(|wheel| wheel.encoder.get_position())
+			.collect::<Result<_, _>>()?;
+
+		for (wheel, position) in self.wheels.iter_mut().zip(positions) {
+			wheel.record(position);
+		}
+
+		Ok(())
+	}
+}
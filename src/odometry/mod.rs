@@ -0,0 +1,300 @@
+use core::convert::Infallible;
+
+use uom::{
+	si::{
+		f64::{Angle, Length, Time},
+		length::meter,
+		time::millisecond,
+	},
+	ConstZero,
+};
+
+use crate::{
+	clock::{Clock, DefaultClock},
+	coordinates::Position,
+	encoder::Encoder,
+	inertial::{InertialSensor, NoInertialSensor},
+};
+
+mod calibration;
+mod covariance;
+mod fusion;
+mod integration;
+mod layout;
+mod motion;
+#[cfg(feature = "vex-rt")]
+mod task;
+
+#[cfg(feature = "vex-rt")]
+pub use calibration::CalibrationRunError;
+pub use calibration::{Calibration, CalibrationError, Calibrator, WheelCalibration};
+pub use covariance::PoseCovariance;
+use fusion::HeadingFilter;
+pub use fusion::HeadingFusion;
+pub use integration::Integration;
+use layout::LocalMovement;
+pub use layout::{LayoutError, TrackingLayout, TrackingWheel, WheelOrientation};
+use motion::MotionFilter;
+pub use motion::{PoseAcceleration, PoseVelocity};
+#[cfg(feature = "vex-rt")]
+pub use task::{OdometryHandle, OdometryState};
+
+/// Time constant of the low-pass filter on velocity and acceleration unless another is given
+const VELOCITY_FILTER_MILLISECONDS: f64 = 50.0;
+
+/// Error returned when an [`OdometrySystem`] cannot be set up or one of the sensors feeding it cannot be read
+#[derive(Debug)]
+pub enum OdometryError<E, I> {
+	/// The tracking layout cannot be used to follow the robot
+	Layout(LayoutError),
+	/// A tracking wheel encoder failed
+	Encoder(E),
+	/// The inertial sensor failed
+	Inertial(I),
+}
+
+/// Inertial sensor along with the filter fusing its heading into the odometry
+struct InertialHeading<I> {
+	sensor: I,
+	filter: HeadingFilter,
+	/// Difference between the odometry heading and the sensor's rotation
+	offset: Option<Angle>,
+}
+
+impl<I: InertialSensor> InertialHeading<I> {
+	/// Fuses the heading change measured by the wheels with a rotation read from the sensor, returning the fused
+	/// heading change
+	fn heading_change(&mut self, heading: Angle, wheel_change: Option<Angle>, rotation: Angle) -> Angle {
+		let offset: Angle = *self.offset.get_or_insert(heading - rotation);
+
+		self.filter.fuse(heading, wheel_change, rotation + offset) - heading
+	}
+
+	/// Lines the sensor up with a new heading, or on the next cycle if it cannot be read right now
+	fn align(&mut self, heading: Angle) {
+		self.offset = self
+			.sensor
+			.get_rotation()
+			.ok()
+			.map(|rotation: Angle| heading - rotation);
+	}
+}
+
+pub struct OdometrySystem<E, I = NoInertialSensor, C = DefaultClock> {
+	layout: TrackingLayout<E>,
+
+	x_state: Length,
+	y_state: Length,
+	heading_state: Angle,
+	covariance: PoseCovariance,
+	integration: Integration,
+
+	motion: MotionFilter,
+	clock: C,
+	previous_time: Time,
+
+	inertial: Option<InertialHeading<I>>,
+}
+
+impl<E: Encoder> OdometrySystem<E> {
+	/// Creates an odometry system which takes its heading from the tracking wheels alone
+	pub fn new(layout: TrackingLayout<E>) -> Result<Self, OdometryError<E::Error, Infallible>> {
+		layout.validate(false).map_err(OdometryError::Layout)?;
+		Self::build(layout, None)
+	}
+}
+
+impl<E: Encoder, I: InertialSensor> OdometrySystem<E, I> {
+	/// Creates an odometry system which fuses the heading from an inertial sensor with the heading measured by the
+	/// tracking wheels
+	///
+	/// Layouts which cannot measure rotation with their wheels take the heading straight from the sensor, whatever the
+	/// fusion strategy.
+	pub fn new_with_inertial(
+		layout: TrackingLayout<E>, inertial_sensor: I, fusion: HeadingFusion,
+	) -> Result<Self, OdometryError<E::Error, I::Error>> {
+		layout.validate(true).map_err(OdometryError::Layout)?;
+
+		Self::build(
+			layout,
+			Some(InertialHeading {
+				// Line the sensor up with the starting heading straight away if it can be read, otherwise on the
+				// first cycle
+				offset: inertial_sensor.get_rotation().ok().map(|rotation: Angle| -rotation),
+				sensor: inertial_sensor,
+				filter: HeadingFilter::new(fusion),
+			}),
+		)
+	}
+
+	fn build(
+		mut layout: TrackingLayout<E>, inertial: Option<InertialHeading<I>>,
+	) -> Result<Self, OdometryError<E::Error, I::Error>> {
+		layout.reset().map_err(OdometryError::Encoder)?;
+
+		let clock: DefaultClock = DefaultClock::default();
+
+		Ok(Self {
+			layout,
+
+			x_state: Length::ZERO,
+			y_state: Length::ZERO,
+			heading_state: Angle::ZERO,
+			covariance: PoseCovariance::default(),
+			integration: Integration::default(),
+
+			motion: MotionFilter::new(Time::new::<millisecond>(VELOCITY_FILTER_MILLISECONDS)),
+			previous_time: clock.now(),
+			clock,
+
+			inertial,
+		})
+	}
+}
+
+impl<E: Encoder, I: InertialSensor, C: Clock> OdometrySystem<E, I, C> {
+	/// Measures time for the velocity and acceleration estimates with the given clock, such as the clock of a
+	/// simulation
+	pub fn with_clock<D: Clock>(self, clock: D) -> OdometrySystem<E, I, D> {
+		OdometrySystem {
+			layout: self.layout,
+			x_state: self.x_state,
+			y_state: self.y_state,
+			heading_state: self.heading_state,
+			covariance: self.covariance,
+			integration: self.integration,
+			motion: self.motion,
+			previous_time: clock.now(),
+			clock,
+			inertial: self.inertial,
+		}
+	}
+
+	/// Sets the scheme used to integrate the movement measured each cycle, which defaults to
+	/// [`Integration::ExactArc`]
+	pub fn with_integration(mut self, integration: Integration) -> Self {
+		self.integration = integration;
+		self
+	}
+
+	/// Sets the time constant of the low-pass filter on the velocity and acceleration estimates, which defaults to
+	/// 50 ms
+	///
+	/// Longer time constants give smoother estimates which lag further behind the robot.
+	pub fn with_velocity_filter(mut self, time_constant: Time) -> Self {
+		self.motion.time_constant = time_constant;
+		self
+	}
+
+	pub fn get_position(&self) -> Position { Position::new(self.x_state, self.y_state, self.heading_state) }
+
+	/// Gets the velocity of the robot along the field axes
+	pub fn get_velocity(&self) -> PoseVelocity { self.motion.velocity() }
+
+	/// Gets the velocity of the robot in its own frame, with x to its right and y forwards
+	pub fn get_local_velocity(&self) -> PoseVelocity { self.motion.local_velocity(self.heading_state) }
+
+	/// Gets the acceleration of the robot along the field axes
+	pub fn get_acceleration(&self) -> PoseAcceleration { self.motion.acceleration() }
+
+	/// Gets the acceleration of the robot in its own frame, with x to its right and y forwards
+	///
+	/// This is the field acceleration turned to line up with the robot, as an accelerometer on the robot would read
+	/// it, so turning at speed shows up as sideways acceleration.
+	pub fn get_local_acceleration(&self) -> PoseAcceleration { self.motion.local_acceleration(self.heading_state) }
+
+	/// Gets how uncertain the tracked position is, which grows as the robot moves and shrinks when it is set
+	///
+	/// The uncertainty comes from the noise of each [`TrackingWheel`]. When an inertial sensor supplies the heading,
+	/// the heading it gives is taken to be exact.
+	pub fn get_covariance(&self) -> PoseCovariance { self.covariance }
+
+	/// Moves the tracked position to a known position on the field, such as a
+	/// [`StartingTile`](crate::field::StartingTile)
+	pub fn set_position(&mut self, position: Position) {
+		let Position(coordinates, heading) = position;
+
+		self.set_x(coordinates.x);
+		self.set_y(coordinates.y);
+		self.set_heading(heading);
+	}
+
+	/// Overrides the tracked x coordinate, such as after squaring up against a wall
+	pub fn set_x(&mut self, x: Length) { self.correct_x(x, Length::ZERO); }
+
+	/// Overrides the tracked y coordinate, such as after squaring up against a wall
+	pub fn set_y(&mut self, y: Length) { self.correct_y(y, Length::ZERO); }
+
+	/// Moves the tracked x coordinate to a measured value, such as from a distance sensor, which is uncertain by a
+	/// standard deviation
+	pub fn correct_x(&mut self, x: Length, deviation: Length) {
+		let deviation: f64 = deviation.get::<meter>();
+
+		self.x_state = x;
+		self.covariance.reset(0, deviation * deviation);
+	}
+
+	/// Moves the tracked y coordinate to a measured value, such as from a distance sensor, which is uncertain by a
+	/// standard deviation
+	pub fn correct_y(&mut self, y: Length, deviation: Length) {
+		let deviation: f64 = deviation.get::<meter>();
+
+		self.y_state = y;
+		self.covariance.reset(1, deviation * deviation);
+	}
+
+	/// Overrides the tracked heading, lining the inertial sensor up with it
+	pub fn set_heading(&mut self, heading: Angle) {
+		self.heading_state = heading;
+		self.covariance.reset(2, 0.0);
+
+		if let Some(inertial) = &mut self.inertial {
+			inertial.align(heading);
+		}
+	}
+
+	pub fn cycle(&mut self) -> Result<(), OdometryError<E::Error, I::Error>> {
+		// The inertial sensor is read before the encoders, as reading the encoders consumes their movement since the
+		// last cycle, which would be lost if the inertial sensor then failed
+		let rotation: Option<Angle> = match &self.inertial {
+			Some(inertial) => Some(inertial.sensor.get_rotation().map_err(OdometryError::Inertial)?),
+			None => None,
+		};
+
+		self.layout.read().map_err(OdometryError::Encoder)?;
+
+		let wheel_angle_change: Option<Angle> = self.layout.solve(None).map(|movement| movement.rotation);
+
+		let local_angle_change: Angle = match (&mut self.inertial, rotation) {
+			(Some(inertial), Some(rotation)) => {
+				inertial.heading_change(self.heading_state, wheel_angle_change, rotation)
+			},
+			_ => wheel_angle_change.unwrap_or_default(),
+		};
+
+		let movement: LocalMovement = self.layout.solve(Some(local_angle_change)).unwrap_or_default();
+
+		let (x_change, y_change): (Length, Length) = self.integration.field_change(self.heading_state, movement);
+
+		self.covariance.propagate(
+			self.heading_state + (local_angle_change / 2.0),
+			movement,
+			self.layout.movement_covariance(self.inertial.is_some()),
+		);
+
+		let x: Length = self.x_state + x_change;
+		let y: Length = self.y_state + y_change;
+		let heading: Angle = self.heading_state + local_angle_change;
+
+		self.x_state = x;
+		self.y_state = y;
+		self.heading_state = heading;
+
+		let now: Time = self.clock.now();
+		self.motion
+			.update(x_change, y_change, local_angle_change, now - self.previous_time);
+		self.previous_time = now;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,121 @@
+use uom::{
+	si::f64::{Acceleration, Angle, AngularAcceleration, AngularVelocity, Length, Ratio, Time, Velocity},
+	ConstZero,
+};
+
+use crate::math::{RealAngle, RealSquare};
+
+/// Rate of change of a position
+///
+/// In the field frame x and y follow the field axes. In the robot frame x is to the right of the robot and y is
+/// forwards. The angular velocity is clockwise in both.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PoseVelocity {
+	/// Velocity along the x axis
+	pub x: Velocity,
+	/// Velocity along the y axis
+	pub y: Velocity,
+	/// Clockwise angular velocity
+	pub angular: AngularVelocity,
+}
+
+impl PoseVelocity {
+	/// Speed the robot is moving at, in whatever direction
+	pub fn speed(&self) -> Velocity { (self.x * self.x + self.y * self.y).sqrt() }
+}
+
+/// Rate of change of a [`PoseVelocity`], with the same axes
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PoseAcceleration {
+	/// Acceleration along the x axis
+	pub x: Acceleration,
+	/// Acceleration along the y axis
+	pub y: Acceleration,
+	/// Clockwise angular acceleration
+	pub angular: AngularAcceleration,
+}
+
+impl PoseAcceleration {
+	/// Magnitude of the acceleration, in whatever direction
+	pub fn magnitude(&self) -> Acceleration { (self.x * self.x + self.y * self.y).sqrt() }
+}
+
+/// Low-pass filtered velocity and acceleration, differentiated from the change in position each cycle
+pub(super) struct MotionFilter {
+	pub(super) time_constant: Time,
+	velocity: PoseVelocity,
+	acceleration: PoseAcceleration,
+}
+
+impl MotionFilter {
+	pub(super) fn new(time_constant: Time) -> Self {
+		Self {
+			time_constant,
+			velocity: PoseVelocity::default(),
+			acceleration: PoseAcceleration::default(),
+		}
+	}
+
+	/// Updates the estimates with the change in field position over a period of time
+	pub(super) fn update(&mut self, x_change: Length, y_change: Length, heading_change: Angle, delta_time: Time) {
+		if delta_time <= Time::ZERO {
+			return;
+		}
+
+		// Share of the new measurement taken in, which makes a first order low-pass filter with the given time constant
+		let weight: Ratio = delta_time / (self.time_constant + delta_time);
+
+		let previous: PoseVelocity = self.velocity;
+
+		self.velocity.x += (x_change / delta_time - previous.x) * weight;
+		self.velocity.y += (y_change / delta_time - previous.y) * weight;
+		let angular_velocity: AngularVelocity = (heading_change / delta_time).into();
+		self.velocity.angular += ((angular_velocity - previous.angular) * weight).into();
+
+		let acceleration_x: Acceleration = (self.velocity.x - previous.x) / delta_time;
+		let acceleration_y: Acceleration = (self.velocity.y - previous.y) / delta_time;
+		let angular_acceleration: AngularAcceleration =
+			((self.velocity.angular - previous.angular) / delta_time).into();
+
+		self.acceleration.x += (acceleration_x - self.acceleration.x) * weight;
+		self.acceleration.y += (acceleration_y - self.acceleration.y) * weight;
+		self.acceleration.angular += ((angular_acceleration - self.acceleration.angular) * weight).into();
+	}
+
+	pub(super) fn velocity(&self) -> PoseVelocity { self.velocity }
+
+	pub(super) fn acceleration(&self) -> PoseAcceleration { self.acceleration }
+
+	/// Velocity in the frame of a robot at a given heading
+	pub(super) fn local_velocity(&self, heading: Angle) -> PoseVelocity {
+		let (x, y) = to_local(self.velocity.x, self.velocity.y, heading);
+
+		PoseVelocity {
+			x,
+			y,
+			angular: self.velocity.angular,
+		}
+	}
+
+	/// Acceleration in the frame of a robot at a given heading
+	pub(super) fn local_acceleration(&self, heading: Angle) -> PoseAcceleration {
+		let (x, y) = to_local(self.acceleration.x, self.acceleration.y, heading);
+
+		PoseAcceleration {
+			x,
+			y,
+			angular: self.acceleration.angular,
+		}
+	}
+}
+
+/// Turns a field vector into the frame of a robot at a given heading, as sideways and forward components
+fn to_local<Q>(x: Q, y: Q, heading: Angle) -> (Q, Q)
+where
+	Q: core::ops::Mul<Ratio, Output = Q> + core::ops::Add<Output = Q> + core::ops::Sub<Output = Q> + Copy,
+{
+	let sin: Ratio = heading.sin();
+	let cos: Ratio = heading.cos();
+
+	(x * cos - y * sin, x * sin + y * cos)
+}
@@ -0,0 +1,115 @@
+use alloc::sync::Arc;
+use core::time::Duration;
+
+use vex_rt::{
+	rtos::{Context, Error, Loop, Mutex, Task},
+	select,
+};
+
+use super::{OdometrySystem, PoseCovariance, PoseVelocity};
+use crate::{clock::Clock, coordinates::Position, encoder::Encoder, inertial::InertialSensor};
+
+/// Latest estimates published by an odometry task
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OdometryState {
+	/// Tracked position on the field
+	pub position: Position,
+	/// Velocity along the field axes
+	pub velocity: PoseVelocity,
+	/// Uncertainty in the tracked position
+	pub covariance: PoseCovariance,
+	/// Number of cycles run so far
+	pub cycles: u32,
+	/// Number of cycles skipped because a sensor could not be read
+	pub failed_cycles: u32,
+}
+
+struct Shared {
+	state: OdometryState,
+	/// Position handed over by another task, applied at the start of the next cycle
+	pending_position: Option<Position>,
+}
+
+/// Cheap handle to an odometry system running in its own task, which can be cloned and shared between tasks
+#[derive(Clone)]
+pub struct OdometryHandle {
+	shared: Arc<Mutex<Shared>>,
+	ctx: Context,
+}
+
+impl OdometryHandle {
+	/// Gets everything the task published after its last cycle
+	pub fn get_state(&self) -> OdometryState { self.shared.lock().state }
+
+	/// Gets the tracked position as of the last cycle
+	pub fn get_position(&self) -> Position { self.get_state().position }
+
+	/// Gets the velocity along the field axes as of the last cycle
+	pub fn get_velocity(&self) -> PoseVelocity { self.get_state().velocity }
+
+	/// Moves the tracked position to a known position, such as one corrected by relocalisation, before the next
+	/// cycle runs
+	pub fn set_position(&self, position: Position) { self.shared.lock().pending_position = Some(position); }
+
+	/// Stops the task, which finishes the cycle it is running first
+	pub fn stop(&self) { self.ctx.cancel(); }
+}
+
+impl<E, I, C> OdometrySystem<E, I, C>
+where
+	E: Encoder + Send + 'static,
+	I: InertialSensor + Send + 'static,
+	C: Clock + Send + 'static,
+{
+	/// Runs the odometry in a new task, cycling once per period until the context is cancelled or the handle is
+	/// stopped
+	pub fn spawn(mut self, period: Duration, ctx: &Context) -> Result<OdometryHandle, Error> {
+		let shared: Arc<Mutex<Shared>> = Arc::new(Mutex::new(Shared {
+			state: OdometryState {
+				position: self.get_position(),
+				covariance: self.get_covariance(),
+				..OdometryState::default()
+			},
+			pending_position: None,
+		}));
+
+		let handle: OdometryHandle = OdometryHandle {
+			shared: shared.clone(),
+			ctx: ctx.fork(),
+		};
+		let ctx: Context = handle.ctx.clone();
+
+		Task::spawn(move || {
+			let mut pause = Loop::new(period);
+
+			loop {
+				if let Some(position) = shared.lock().pending_position.take() {
+					self.set_position(position);
+				}
+
+				let succeeded: bool = self.cycle().is_ok();
+
+				{
+					let mut shared = shared.lock();
+					let state: &mut OdometryState = &mut shared.state;
+
+					state.position = self.get_position();
+					state.velocity = self.get_velocity();
+					state.covariance = self.get_covariance();
+					state.cycles = state.cycles.wrapping_add(1);
+
+					if !succeeded {
+						state.failed_cycles = state.failed_cycles.wrapping_add(1);
+					}
+				}
+
+				select! {
+					_ = ctx.done() => break,
+					_ = pause.select() => continue
+				}
+			}
+		})?;
+
+		Ok(handle)
+	}
+}
@@ -1,147 +1,612 @@
+//! PID controllers generic over the quantity they measure and the quantity they output
+//!
+//! The units of the gains follow from the two quantities, so a controller turning an [`Angle`] into an
+//! [`AngularVelocity`] takes its proportional gain in hertz, while one turning a [`Length`](uom::si::f64::Length) into
+//! an [`ElectricPotential`] takes it in volts per metre.
+
+use core::{
+	marker::PhantomData,
+	ops::{Div, Mul},
+};
+
+use libm::fabs;
 use uom::{
 	si::{
-		angular_acceleration::radian_per_second_squared,
-		electric_potential::volt,
-		f64::{Angle, AngularAbsement, AngularAcceleration, AngularVelocity, ElectricPotential, Time},
+		f64::{Angle, AngularAcceleration, AngularVelocity, ElectricPotential, Time},
+		time::{millisecond, second},
+		Dimension,
+		Quantity,
+		SI,
 	},
 	ConstZero,
 };
-use vex_rt::rtos::{time_since_start, Instant};
 
-use crate::Gains;
+#[cfg(feature = "vex-rt")]
+use crate::clock::RtosClock;
+use crate::{clock::Clock, feedforward::MotorFeedforward, motor::MAX_VOLTAGE, Gains};
+
+/// Quantity a [`Pid`] can measure or output, which it works with in SI base units
+pub trait Signal: Copy {
+	/// Creates the quantity from its value in SI base units
+	fn from_base(value: f64) -> Self;
+
+	/// Gets the value of the quantity in SI base units
+	fn to_base(self) -> f64;
+}
+
+impl<D: Dimension + ?Sized> Signal for Quantity<D, SI<f64>, f64> {
+	fn from_base(value: f64) -> Self {
+		Self {
+			dimension: PhantomData,
+			units: PhantomData,
+			value,
+		}
+	}
+
+	fn to_base(self) -> f64 { self.value }
+}
 
-pub struct PositionController {
-	integral: AngularAbsement,
-	previous_error: Option<Angle>,
-	previous_time: Instant,
+/// Quantity a [`Pid`] can measure, along with the quantities its integral and derivative are measured in
+pub trait PidInput: Signal {
+	/// Quantity the error builds up into over time
+	type Integral: Signal;
+	/// Rate of change of the quantity
+	type Rate: Signal;
+}
 
-	target: Angle,
+impl<T> PidInput for T
+where
+	T: Signal + Mul<Time> + Div<Time>,
+	<T as Mul<Time>>::Output: Signal,
+	<T as Div<Time>>::Output: Signal,
+{
+	type Integral = <T as Mul<Time>>::Output;
+	type Rate = <T as Div<Time>>::Output;
+}
 
-	gains: Gains,
+/// Quantity a [`Pid`] measuring `I` can output, along with the units of the gains which turn one into the other
+pub trait PidOutput<I: PidInput>: Signal {
+	/// Output per unit of error
+	type Proportional: Signal;
+	/// Output per unit of the integral of the error
+	type Integral: Signal;
+	/// Output per unit of the rate of change of the error
+	type Derivative: Signal;
+}
 
-	completion_threshold: Angle,
+impl<I: PidInput, O> PidOutput<I> for O
+where
+	O: Signal + Div<I> + Div<I::Integral> + Div<I::Rate>,
+	<O as Div<I>>::Output: Signal,
+	<O as Div<I::Integral>>::Output: Signal,
+	<O as Div<I::Rate>>::Output: Signal,
+{
+	type Derivative = <O as Div<I::Rate>>::Output;
+	type Integral = <O as Div<I::Integral>>::Output;
+	type Proportional = <O as Div<I>>::Output;
 }
 
-impl PositionController {
-	pub fn new(target: Angle, gains: Gains, completion_threshold: Angle) -> Self {
+/// Gains of a [`Pid`] measuring `I` and outputting `O`
+pub type PidGains<I, O> =
+	Gains<<O as PidOutput<I>>::Proportional, <O as PidOutput<I>>::Integral, <O as PidOutput<I>>::Derivative>;
+
+/// Controller of a position, which outputs the velocity the position should be changing at
+pub type PositionController<C> = Pid<Angle, AngularVelocity, C>;
+
+/// Limits which keep the integral of a [`Pid`] from winding up while the error is large
+///
+/// Every limit is off by default.
+#[derive(Clone, Copy, Debug)]
+pub struct AntiWindup<I: PidInput, O> {
+	/// Largest size the integral can grow to either way
+	pub integral_limit: Option<I::Integral>,
+	/// Largest error the integral builds up over, beyond which it is cleared so that it only corrects the last of the
+	/// error once the proportional channel has done the bulk of the work
+	pub integration_zone: Option<I>,
+	/// Whether to clear the integral when the error changes sign, so that an overshoot is not made worse by the
+	/// integral built up on the way to the target
+	pub reset_on_sign_change: bool,
+	/// Largest output either way
+	pub output_limit: Option<O>,
+	/// Time constant over which the integral is pulled back while the output is saturated, so that it holds steady
+	/// at the value which just saturates the output
+	pub tracking_time: Time,
+}
+
+impl<I: PidInput, O> Default for AntiWindup<I, O> {
+	fn default() -> Self {
 		Self {
-			integral: AngularAbsement::ZERO,
+			integral_limit: None,
+			integration_zone: None,
+			reset_on_sign_change: false,
+			output_limit: None,
+			tracking_time: Time::new::<millisecond>(100.0),
+		}
+	}
+}
+
+/// How far a controller has got towards its target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControllerStatus {
+	/// Still moving towards the target
+	Running,
+	/// Held within the completion threshold of the target, and slowly enough, for the dwell time
+	Settled,
+	/// Ran for longer than the timeout without settling
+	TimedOut,
+	/// Stopped short of the target for longer than the stall time, such as when pushing against a wall
+	Stalled,
+}
+
+impl ControllerStatus {
+	/// Whether the controller has stopped running, one way or another
+	pub fn is_finished(self) -> bool { self != ControllerStatus::Running }
+
+	/// Combines the status of two controllers driving together, which have settled once both have settled and have
+	/// failed as soon as either has failed
+	pub fn and(self, other: ControllerStatus) -> ControllerStatus {
+		use ControllerStatus::*;
+
+		match (self, other) {
+			(Stalled, _) | (_, Stalled) => Stalled,
+			(TimedOut, _) | (_, TimedOut) => TimedOut,
+			(Settled, Settled) => Settled,
+			_ => Running,
+		}
+	}
+}
+
+/// Conditions under which a [`Pid`] is considered to have finished
+#[derive(Clone, Copy, Debug)]
+pub struct Settling<I: PidInput> {
+	/// Time the controller has to stay near the target before it has settled, which rides out passing through the
+	/// target on an overshoot
+	pub dwell_time: Time,
+	/// Fastest the mechanism can be moving and still count as settled or stalled, without which the controller
+	/// settles on the error alone and never stalls
+	pub velocity_threshold: Option<I::Rate>,
+	/// Longest the controller can run for before it gives up, if at all
+	pub timeout: Option<Time>,
+	/// Longest the mechanism can stay still short of the target before the controller gives up, if at all
+	///
+	/// Still means slower than the velocity threshold, so the controller only ever stalls if a velocity threshold is
+	/// set as well, which the default leaves unset.
+	pub stall_time: Option<Time>,
+}
+
+impl<I: PidInput> Default for Settling<I> {
+	fn default() -> Self {
+		Self {
+			dwell_time: Time::new::<millisecond>(100.0),
+			velocity_threshold: None,
+			timeout: None,
+			stall_time: None,
+		}
+	}
+}
+
+/// Signal the derivative channel of a [`Pid`] differentiates
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DerivativeMode {
+	/// The error, which spikes whenever the target jumps
+	#[default]
+	Error,
+	/// The measurement, turned around to match the error, which ignores jumps in the target so that setting a new
+	/// target does not kick the output
+	Measurement,
+}
+
+/// PID controller which measures a quantity `I` and outputs a quantity `O`
+pub struct Pid<I: PidInput, O: PidOutput<I>, C> {
+	integral: I::Integral,
+	previous_error: Option<I>,
+	previous_measurement: Option<I>,
+	filtered_derivative: I::Rate,
+	previous_time: Time,
+
+	target: I,
+
+	gains: PidGains<I, O>,
+	anti_windup: AntiWindup<I, O>,
+	derivative_mode: DerivativeMode,
+	derivative_filter: Time,
+
+	completion_threshold: I,
+	settling: Settling<I>,
+	status: ControllerStatus,
+	start_time: Time,
+	settling_since: Option<Time>,
+	stalled_since: Option<Time>,
+
+	clock: C,
+}
+
+#[cfg(feature = "vex-rt")]
+impl<I: PidInput, O: PidOutput<I>> Pid<I, O, RtosClock> {
+	/// Creates a controller which measures time using the RTOS clock
+	pub fn new(target: I, gains: PidGains<I, O>, completion_threshold: I) -> Self {
+		Self::with_clock(target, gains, completion_threshold, RtosClock)
+	}
+}
+
+impl<I: PidInput, O: PidOutput<I>, C: Clock> Pid<I, O, C> {
+	/// Creates a controller which measures time using the given clock
+	pub fn with_clock(target: I, gains: PidGains<I, O>, completion_threshold: I, clock: C) -> Self {
+		Self {
+			integral: I::Integral::from_base(0.0),
 			previous_error: None,
+			previous_measurement: None,
+			filtered_derivative: I::Rate::from_base(0.0),
 
-			previous_time: time_since_start(),
+			previous_time: clock.now(),
 
 			target,
 
 			gains,
+			anti_windup: AntiWindup::default(),
+			derivative_mode: DerivativeMode::default(),
+			derivative_filter: Time::ZERO,
 
 			completion_threshold,
+			settling: Settling::default(),
+			status: ControllerStatus::Running,
+			start_time: clock.now(),
+			settling_since: None,
+			stalled_since: None,
+
+			clock,
 		}
 	}
 
-	/// Determines whether the controller has reached the target by checking if the error has changed since the last
-	/// cycle
-	pub fn is_complete(&self, current: Angle) -> bool {
-		self.previous_error
-			.map(|previous_error: Angle| (current - self.target) == previous_error)
-			.unwrap_or(false)
+	/// Limits the integral and the output to keep the integral from winding up
+	pub fn with_anti_windup(mut self, anti_windup: AntiWindup<I, O>) -> Self {
+		self.anti_windup = anti_windup;
+		self
+	}
+
+	/// Sets the signal the derivative channel differentiates, which defaults to the error
+	pub fn with_derivative_mode(mut self, derivative_mode: DerivativeMode) -> Self {
+		self.derivative_mode = derivative_mode;
+		self
+	}
+
+	/// Smooths the derivative channel with a low-pass filter of the given time constant, which is off by default
+	///
+	/// Longer time constants take out more of the noise in the measurement but make the derivative lag further
+	/// behind.
+	pub fn with_derivative_filter(mut self, time_constant: Time) -> Self {
+		self.derivative_filter = time_constant;
+		self
+	}
+
+	/// Sets the conditions under which the controller has settled, timed out or stalled
+	pub fn with_settling(mut self, settling: Settling<I>) -> Self {
+		self.settling = settling;
+		self
+	}
+
+	/// Gets how far the controller has got towards the target as of the last cycle
+	///
+	/// Once the controller has finished its status stays the same until the target is set again.
+	pub fn status(&self) -> ControllerStatus { self.status }
+
+	/// Gets the target that the controller is aiming for
+	pub fn target(&self) -> I { self.target }
+
+	/// Gets the gains the controller is running with
+	pub fn gains(&self) -> PidGains<I, O> { self.gains }
+
+	/// Replaces the gains the controller is running with, keeping the integral and the settling conditions as they
+	/// are
+	pub fn set_gains(&mut self, gains: PidGains<I, O>) { self.gains = gains; }
+
+	/// Sets the current target that the controller is aiming for, starting the settling conditions over
+	pub fn set_target(&mut self, target: I) {
+		self.target = target;
+
+		self.status = ControllerStatus::Running;
+		self.start_time = self.clock.now();
+		self.settling_since = None;
+		self.stalled_since = None;
+	}
+
+	/// Moves the target without starting the timeout or the stall time over, such as to follow a motion profile
+	///
+	/// The dwell time is started over, since the mechanism has yet to hold the new target, so a controller whose
+	/// target is moved every cycle can time out or stall along the way but only settles once the target stops moving.
+	pub fn track_target(&mut self, target: I) {
+		self.target = target;
+
+		self.settling_since = None;
+
+		if self.status == ControllerStatus::Settled {
+			self.status = ControllerStatus::Running;
+		}
 	}
 
-	/// Sets the current target that the controller is aiming for
-	pub fn set_target(&mut self, target: Angle) { self.target = target; }
+	/// Runs a cycle of the PID and returns its output
+	pub fn cycle(&mut self, current: I) -> O {
+		let error: f64 = self.target.to_base() - current.to_base();
+		let current: f64 = current.to_base();
+
+		let now: Time = self.clock.now();
+		let delta_time: f64 = (now - self.previous_time).get::<second>();
+
+		let previous_error: f64 = self.previous_error.map_or(error, I::to_base);
 
-	/// Runs a cycle of the PID and returns the velocity the controller determines the motors should be moving at
-	pub fn cycle(&mut self, current: Angle) -> AngularVelocity {
-		let error: Angle = self.target - current;
+		let crossed_target: bool = error * previous_error < 0.0;
+		let outside_zone: bool =
+			matches!(self.anti_windup.integration_zone, Some(zone) if fabs(error) > fabs(zone.to_base()));
 
-		let delta_time: Time = (time_since_start() - self.previous_time).try_into().unwrap();
+		let mut integral: f64 = if (self.anti_windup.reset_on_sign_change && crossed_target) || outside_zone {
+			0.0
+		} else {
+			self.integral.to_base() + error * delta_time
+		};
 
-		self.integral += (error * delta_time).into();
+		integral = self.clamp_integral(integral);
 
-		let previous_error: Angle = self.previous_error.unwrap_or(error);
+		let previous_measurement: f64 = self.previous_measurement.map_or(current, I::to_base);
+		let velocity: f64 = rate(current - previous_measurement, delta_time);
 
-		let derivative: AngularVelocity = ((error - previous_error) / delta_time).into();
+		let raw_derivative: f64 = match self.derivative_mode {
+			DerivativeMode::Error => rate(error - previous_error, delta_time),
+			DerivativeMode::Measurement => -velocity,
+		};
+
+		let mut derivative: f64 = self.filtered_derivative.to_base();
+
+		if delta_time > 0.0 {
+			// Share of the new derivative taken in, which makes a first order low-pass filter with the given time
+			// constant
+			let weight: f64 = delta_time / (self.derivative_filter.get::<second>() + delta_time);
+			derivative += (raw_derivative - derivative) * weight;
+		}
 
-		self.previous_error = Some(error);
+		self.filtered_derivative = I::Rate::from_base(derivative);
 
-		self.previous_time = time_since_start();
+		self.previous_error = Some(I::from_base(error));
+		self.previous_measurement = Some(I::from_base(current));
 
-		let proportional_channel: AngularVelocity = (self.gains.proportional * error).into();
+		self.previous_time = now;
 
-		let integral_channel: AngularVelocity = (self.gains.integral * self.integral).into();
+		self.update_status(error, velocity, now);
 
-		let derivative_channel: AngularVelocity = (self.gains.derivative * derivative).into();
+		let proportional_gain: f64 = self.gains.proportional.to_base();
+		let integral_gain: f64 = self.gains.integral.to_base();
+		let derivative_gain: f64 = self.gains.derivative.to_base();
 
-		proportional_channel + integral_channel + derivative_channel
+		let output: f64 = proportional_gain * error + integral_gain * integral + derivative_gain * derivative;
+
+		let limit: f64 = match self.anti_windup.output_limit {
+			Some(limit) => limit.to_base(),
+			None => {
+				self.integral = I::Integral::from_base(integral);
+				return O::from_base(output);
+			},
+		};
+
+		let saturated: f64 = clamp(output, limit);
+
+		// Back-calculation, which bleeds off the integral by however much the output had to be cut back
+		if saturated != output && integral_gain != 0.0 {
+			let tracking: f64 = (delta_time / self.anti_windup.tracking_time.get::<second>()).min(1.0);
+			let excess: f64 = (saturated - output) / integral_gain;
+
+			integral = self.clamp_integral(integral + excess * tracking);
+		}
+
+		self.integral = I::Integral::from_base(integral);
+
+		O::from_base(saturated)
+	}
+
+	/// Moves the status on from running once the settling conditions have been met
+	fn update_status(&mut self, error: f64, velocity: f64, now: Time) {
+		if self.status.is_finished() {
+			return;
+		}
+
+		let on_target: bool = fabs(error) <= fabs(self.completion_threshold.to_base());
+		let still: Option<bool> = self
+			.settling
+			.velocity_threshold
+			.map(|threshold: I::Rate| fabs(velocity) <= fabs(threshold.to_base()));
+
+		let settling_since: Option<Time> =
+			(on_target && still != Some(false)).then(|| self.settling_since.unwrap_or(now));
+		let stalled_since: Option<Time> =
+			(!on_target && still == Some(true)).then(|| self.stalled_since.unwrap_or(now));
+
+		self.settling_since = settling_since;
+		self.stalled_since = stalled_since;
+
+		self.status = if matches!(settling_since, Some(since) if now - since >= self.settling.dwell_time) {
+			ControllerStatus::Settled
+		} else if matches!((stalled_since, self.settling.stall_time), (Some(since), Some(stall_time)) if now - since >= stall_time)
+		{
+			ControllerStatus::Stalled
+		} else if matches!(self.settling.timeout, Some(timeout) if now - self.start_time >= timeout) {
+			ControllerStatus::TimedOut
+		} else {
+			ControllerStatus::Running
+		};
+	}
+
+	fn clamp_integral(&self, integral: f64) -> f64 {
+		match self.anti_windup.integral_limit {
+			Some(limit) => clamp(integral, limit.to_base()),
+			None => integral,
+		}
 	}
 }
 
-pub struct VelocityController {
+/// Controller which holds a motor at a target velocity by choosing the voltage to apply to it
+pub trait VelocityControl {
+	/// Sets the velocity the controller is aiming for
+	fn set_target(&mut self, target: AngularVelocity);
+
+	/// Whether the motor is close enough to the target to count as being at speed
+	fn is_at_speed(&self, current: AngularVelocity) -> bool;
+
+	/// Runs a cycle of the controller and returns the voltage to apply to the motor
+	fn cycle(&mut self, current: AngularVelocity) -> ElectricPotential;
+}
+
+/// Controller of a motor's velocity in velocity form, which outputs the voltage to apply to the motor
+///
+/// Each cycle works out how much the voltage should change by, rather than the voltage itself, and adds that to the
+/// voltage from the last cycle. The proportional gain acts on the change in the error, the integral gain on the error
+/// and the derivative gain on the change in the change of the error, so the voltage holds steady once the motor is at
+/// speed without any integral having to be kept. The voltage is held within [`MAX_VOLTAGE`] either way, which stops it
+/// winding up while the motor cannot keep up.
+pub struct VelocityController<C> {
 	previous_error: Option<AngularVelocity>,
 	previous_previous_error: Option<AngularVelocity>,
-	previous_output: AngularAcceleration,
-	previous_time: Instant,
+	feedback: ElectricPotential,
+	previous_target: Option<AngularVelocity>,
+	previous_time: Time,
 
 	target: AngularVelocity,
 
-	gains: Gains,
+	gains: PidGains<AngularVelocity, ElectricPotential>,
+	feedforward: Option<MotorFeedforward>,
 
 	target_threshold: AngularVelocity,
+
+	clock: C,
 }
 
-impl VelocityController {
-	pub fn new(target: AngularVelocity, gains: Gains, target_threshold: AngularVelocity) -> Self {
+#[cfg(feature = "vex-rt")]
+impl VelocityController<RtosClock> {
+	/// Creates a controller which measures time using the RTOS clock
+	pub fn new(
+		target: AngularVelocity, gains: PidGains<AngularVelocity, ElectricPotential>, target_threshold: AngularVelocity,
+	) -> Self {
+		Self::with_clock(target, gains, target_threshold, RtosClock)
+	}
+}
+
+impl<C: Clock> VelocityController<C> {
+	/// Creates a controller which measures time using the given clock
+	pub fn with_clock(
+		target: AngularVelocity, gains: PidGains<AngularVelocity, ElectricPotential>,
+		target_threshold: AngularVelocity, clock: C,
+	) -> Self {
 		Self {
 			previous_error: None,
 			previous_previous_error: None,
-			previous_output: AngularAcceleration::ZERO,
+			feedback: ElectricPotential::ZERO,
+			previous_target: None,
 
-			previous_time: time_since_start(),
+			previous_time: clock.now(),
 
 			target,
 
 			gains,
+			feedforward: None,
 
 			target_threshold,
+
+			clock,
 		}
 	}
 
+	/// Adds the voltage a motor model predicts the target needs to the feedback, leaving the feedback to correct only
+	/// what the model gets wrong
+	///
+	/// The acceleration given to the model is how fast the target is changing.
+	pub fn with_feedforward(mut self, feedforward: MotorFeedforward) -> Self {
+		self.feedforward = Some(feedforward);
+		self
+	}
+
+	/// Whether the motor is within the target threshold of the target
 	pub fn is_at_speed(&self, current: AngularVelocity) -> bool {
 		let error: AngularVelocity = self.target - current;
 		error.abs() <= self.target_threshold
 	}
 
+	/// Sets the velocity the controller is aiming for
 	pub fn set_target(&mut self, target: AngularVelocity) { self.target = target; }
 
+	/// Clears the voltage built up by the feedback and the errors remembered from previous cycles, such as after the
+	/// motor has been driven by something else for a while
+	pub fn reset(&mut self) {
+		self.previous_error = None;
+		self.previous_previous_error = None;
+		self.feedback = ElectricPotential::ZERO;
+		self.previous_target = None;
+		self.previous_time = self.clock.now();
+	}
+
+	/// Runs a cycle of the PID and returns the voltage to apply to the motor
 	pub fn cycle(&mut self, current: AngularVelocity) -> ElectricPotential {
-		let error: AngularVelocity = self.target - current;
+		let error: f64 = (self.target - current).to_base();
+
+		let previous_error: f64 = self.previous_error.map_or(error, Signal::to_base);
+		let previous_previous_error: f64 = self.previous_previous_error.map_or(previous_error, Signal::to_base);
+
+		let now: Time = self.clock.now();
+		let delta_time: f64 = (now - self.previous_time).get::<second>();
 
-		let previous_error: AngularVelocity = self.previous_error.unwrap_or(error);
-		let previous_previous_error: AngularVelocity = self.previous_previous_error.unwrap_or(error);
+		let target_acceleration: AngularAcceleration = AngularAcceleration::from_base(rate(
+			(self.target - self.previous_target.unwrap_or(self.target)).to_base(),
+			delta_time,
+		));
 
-		let delta_error: AngularVelocity = error - previous_error;
+		let proportional_channel: f64 = self.gains.proportional.to_base() * (error - previous_error);
+		let integral_channel: f64 = self.gains.integral.to_base() * error * delta_time;
+		let derivative_channel: f64 =
+			self.gains.derivative.to_base() * rate(error - 2.0 * previous_error + previous_previous_error, delta_time);
 
-		let delta_time: Time = (time_since_start() - self.previous_time).try_into().unwrap();
+		self.previous_previous_error = Some(AngularVelocity::from_base(previous_error));
+		self.previous_error = Some(AngularVelocity::from_base(error));
+		self.previous_target = Some(self.target);
 
-		let integral: Angle = (error * delta_time).into();
-		let derivative: AngularAcceleration =
-			((error - previous_error * 2.0 + previous_previous_error) / delta_time).into();
+		self.previous_time = now;
 
-		self.previous_previous_error = Some(previous_error);
-		self.previous_error = Some(error);
+		let feedforward: ElectricPotential = self
+			.feedforward
+			.map(|feedforward: MotorFeedforward| feedforward.calculate(self.target, target_acceleration))
+			.unwrap_or_default();
 
-		self.previous_time = time_since_start();
+		let feedback: ElectricPotential =
+			self.feedback + ElectricPotential::from_base(proportional_channel + integral_channel + derivative_channel);
 
-		let proportional_channel: AngularAcceleration = (self.gains.proportional * delta_error).into();
-		let integral_channel: AngularAcceleration = (self.gains.integral * integral).into();
-		let derivative_channel: AngularAcceleration = (self.gains.derivative * derivative).into();
+		// Holding the feedback to whatever keeps the total within the motor's range is what stops it winding up
+		self.feedback = feedback.max(-MAX_VOLTAGE - feedforward).min(MAX_VOLTAGE - feedforward);
+
+		(feedforward + self.feedback).max(-MAX_VOLTAGE).min(MAX_VOLTAGE)
+	}
+}
 
-		let output: AngularAcceleration =
-			self.previous_output + proportional_channel + integral_channel + derivative_channel;
+impl<C: Clock> VelocityControl for VelocityController<C> {
+	fn set_target(&mut self, target: AngularVelocity) { VelocityController::set_target(self, target); }
+
+	fn is_at_speed(&self, current: AngularVelocity) -> bool { VelocityController::is_at_speed(self, current) }
+
+	fn cycle(&mut self, current: AngularVelocity) -> ElectricPotential { VelocityController::cycle(self, current) }
+}
+
+/// Divides a change by the time it took in seconds, treating a change over no time as no change rather than producing
+/// NaN
+fn rate(change: f64, delta_time: f64) -> f64 {
+	if delta_time > 0.0 {
+		change / delta_time
+	} else {
+		0.0
+	}
+}
 
-		self.previous_output = output;
+/// Holds a value within a limit either way, whatever the sign of the limit, leaving it as it is if the limit is not a
+/// number
+fn clamp(value: f64, limit: f64) -> f64 {
+	let limit: f64 = fabs(limit);
 
-		ElectricPotential::new::<volt>(
-			(proportional_channel + integral_channel + derivative_channel).get::<radian_per_second_squared>(),
-		)
+	if limit.is_nan() {
+		value
+	} else {
+		value.clamp(-limit, limit)
 	}
 }
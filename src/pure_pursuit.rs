@@ -1,13 +1,15 @@
 use alloc::vec::Vec;
 
+#[cfg(feature = "vex-rt")]
+use uom::si::angle::degree;
 use uom::{
 	si::{
-		angle::degree,
 		f64::{Angle, Area, Length},
 		length::inch,
 	},
 	ConstZero,
 };
+#[cfg(feature = "vex-rt")]
 use vex_rt::prelude::println;
 
 use crate::{
@@ -111,6 +113,7 @@ impl PurePursuitSystem {
 					self.current_node += 1;
 				}
 
+				#[cfg(feature = "vex-rt")]
 				println!(
 					"Robot: ({:.2}in, {:.2}in, {:.2}deg) Next: ({:.2}in, {:.2}in) Target: ({:.2}in, {:.2}in) {:.2}in, \
 					 {:.2}deg",
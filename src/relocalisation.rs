@@ -0,0 +1,169 @@
+//! Correction of odometry drift from distance sensors pointed at the field walls
+//!
+//! Each sensor's reading is compared with the distance the odometry expects it to read to the wall it points at, and
+//! the difference moves the position along the axis that wall fixes. Readings taken too far from a wall, at too
+//! shallow an angle to it, or implying too large a jump are thrown away, since they most likely hit a game element or
+//! another robot instead.
+
+use alloc::vec::Vec;
+
+use libm::sqrt;
+use uom::{
+	si::{
+		angle::degree,
+		f64::{Angle, Length, Ratio},
+		length::inch,
+	},
+	ConstZero,
+};
+
+use crate::{
+	clock::Clock,
+	coordinates::Position,
+	distance::{DistanceSensor, SensorMount},
+	encoder::Encoder,
+	field::cast_to_wall,
+	inertial::InertialSensor,
+	math::RealAngle,
+	odometry::{OdometrySystem, PoseCovariance},
+};
+
+/// Set of distance sensors used to correct the position of the robot against the field walls
+pub struct Relocaliser<D> {
+	sensors: Vec<(D, SensorMount)>,
+
+	/// Longest distance to a wall that is trusted, the sensors lose accuracy and pick up other objects further out
+	pub max_range: Length,
+	/// Largest angle between a sensor and the wall it points at, measured from straight on, beyond which the beam
+	/// glances off the wall
+	pub max_incidence: Angle,
+	/// Largest correction a single reading may make before it is rejected as an outlier
+	pub max_correction: Length,
+	/// Standard deviation of the error in a single reading, which sets how uncertain a corrected coordinate is
+	pub sensor_noise: Length,
+}
+
+/// Position corrected by a [`Relocaliser`], along with how many readings went into correcting each coordinate
+#[derive(Clone, Copy, Debug)]
+pub struct Relocalisation {
+	/// Corrected position, with each coordinate no reading fixed left as it was
+	pub position: Position,
+	/// Number of readings which fixed the x coordinate
+	pub x_readings: usize,
+	/// Number of readings which fixed the y coordinate
+	pub y_readings: usize,
+}
+
+impl<D> Default for Relocaliser<D> {
+	fn default() -> Self {
+		Self {
+			sensors: Vec::new(),
+			max_range: Length::new::<inch>(48.0),
+			max_incidence: Angle::new::<degree>(30.0),
+			max_correction: Length::new::<inch>(4.0),
+			sensor_noise: Length::new::<inch>(0.6),
+		}
+	}
+}
+
+impl<D> Relocaliser<D> {
+	/// Creates a relocaliser without any sensors, trusting readings within two tiles of a wall, up to 30° off
+	/// straight on and correcting by up to 4 inches, with readings accurate to around 0.6 inches
+	pub fn new() -> Self { Self::default() }
+
+	/// Adds a sensor mounted at a known position on the robot
+	pub fn add_sensor(&mut self, sensor: D, mount: SensorMount) { self.sensors.push((sensor, mount)); }
+}
+
+impl<D: DistanceSensor> Relocaliser<D> {
+	/// Corrects a position against the walls the sensors can see, leaving the heading alone
+	///
+	/// Each coordinate is moved by the average correction of the readings which fix it, and left as it is if no
+	/// reading does. Sensors which cannot be read are skipped.
+	pub fn relocalise(&self, position: Position) -> Relocalisation {
+		let Position(coordinates, heading) = position;
+
+		let mut x_correction: Length = Length::ZERO;
+		let mut x_readings: usize = 0;
+		let mut y_correction: Length = Length::ZERO;
+		let mut y_readings: usize = 0;
+
+		for (sensor, mount) in self.sensors.iter() {
+			let sensor_position: Position = mount.locate(position);
+			let Position(_, beam) = sensor_position;
+
+			let (wall, expected) = match cast_to_wall(sensor_position) {
+				Some(hit) => hit,
+				None => continue,
+			};
+
+			// Component of the beam along the axis the wall fixes
+			let direction: Ratio = if wall.is_side() { beam.sin() } else { beam.cos() };
+
+			if expected > self.max_range || direction.abs() < self.max_incidence.cos() {
+				continue;
+			}
+
+			let distance: Length = match sensor.get_distance() {
+				Ok(distance) if distance <= self.max_range => distance,
+				_ => continue,
+			};
+
+			let correction: Length = (expected - distance) * direction;
+
+			if correction.abs() > self.max_correction {
+				continue;
+			}
+
+			if wall.is_side() {
+				x_correction += correction;
+				x_readings += 1;
+			} else {
+				y_correction += correction;
+				y_readings += 1;
+			}
+		}
+
+		let mut corrected: Position = Position::new(coordinates.x, coordinates.y, heading);
+
+		if x_readings > 0 {
+			corrected.0.x += x_correction / x_readings as f64;
+		}
+
+		if y_readings > 0 {
+			corrected.0.y += y_correction / y_readings as f64;
+		}
+
+		Relocalisation {
+			position: corrected,
+			x_readings,
+			y_readings,
+		}
+	}
+
+	/// Corrects the position tracked by an odometry system, returning the corrected position
+	///
+	/// Only the coordinates which readings fixed are moved, and their uncertainty drops to that of the average of the
+	/// readings if the odometry was any less certain. The uncertainty of the rest is left alone.
+	pub fn correct<E: Encoder, I: InertialSensor, C: Clock>(&self, odometry: &mut OdometrySystem<E, I, C>) -> Position {
+		let Relocalisation {
+			position: Position(coordinates, _),
+			x_readings,
+			y_readings,
+		} = self.relocalise(odometry.get_position());
+
+		let covariance: PoseCovariance = odometry.get_covariance();
+
+		if x_readings > 0 {
+			let deviation: Length = self.sensor_noise / sqrt(x_readings as f64);
+			odometry.correct_x(coordinates.x, deviation.min(covariance.x_deviation()));
+		}
+
+		if y_readings > 0 {
+			let deviation: Length = self.sensor_noise / sqrt(y_readings as f64);
+			odometry.correct_y(coordinates.y, deviation.min(covariance.y_deviation()));
+		}
+
+		odometry.get_position()
+	}
+}
@@ -0,0 +1,217 @@
+//! Physics simulations of robot chassis for tuning and testing autonomous routines off the robot
+//!
+//! Simulations hand out [`MockMotor`](crate::motor::MockMotor)s for drive trains to command, [`MockEncoder`]s,
+//! [`MockInertialSensor`]s and [`MockDistanceSensor`]s for odometry to read and a [`ManualClock`] for controllers to
+//! measure time with, then move all of them forward together each time they are stepped.
+
+use alloc::vec::Vec;
+
+use libm::ceil;
+use uom::{
+	si::{
+		angular_velocity::revolution_per_minute,
+		f64::{Angle, AngularVelocity, ElectricPotential, Length, Ratio, Time, Torque},
+		time::millisecond,
+		torque::newton_meter,
+	},
+	ConstZero,
+};
+
+use crate::{
+	clock::ManualClock,
+	coordinates::Position,
+	distance::{MockDistanceSensor, SensorMount},
+	encoder::MockEncoder,
+	field::FieldMap,
+	inertial::MockInertialSensor,
+	math::RealAngle,
+	motor::MAX_VOLTAGE,
+	odometry::WheelOrientation,
+};
+
+mod tank;
+mod x_drive;
+
+pub use tank::{TankChassis, TankSimulation};
+pub use x_drive::{XDriveChassis, XDriveSimulation};
+
+/// Longest period the physics is integrated over in one go, longer steps are broken up into steps of this size
+const SUBSTEP_MILLISECONDS: f64 = 1.0;
+
+/// Furthest a simulated distance sensor can see, matching the range of the V5 distance sensor
+const DISTANCE_SENSOR_RANGE_METERS: f64 = 2.0;
+
+/// Gear cartridge fitted to a V5 smart motor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cartridge {
+	/// 36:1 cartridge, 100 RPM
+	Red,
+	/// 18:1 cartridge, 200 RPM
+	Green,
+	/// 6:1 cartridge, 600 RPM
+	Blue,
+}
+
+impl Cartridge {
+	/// Speed of the output shaft at full voltage with no load
+	pub fn free_speed(self) -> AngularVelocity {
+		AngularVelocity::new::<revolution_per_minute>(match self {
+			Cartridge::Red => 100.0,
+			Cartridge::Green => 200.0,
+			Cartridge::Blue => 600.0,
+		})
+	}
+
+	/// Torque of the output shaft at full voltage when held still
+	pub fn stall_torque(self) -> Torque {
+		Torque::new::<newton_meter>(match self {
+			Cartridge::Red => 2.1,
+			Cartridge::Green => 1.05,
+			Cartridge::Blue => 0.35,
+		})
+	}
+
+	/// Torque produced at the output shaft for an applied voltage while turning at a given speed
+	///
+	/// Uses the linear torque-speed curve of a DC motor, so torque falls off from stall to zero at the free speed
+	/// scaled by the applied voltage.
+	pub fn torque(self, voltage: ElectricPotential, speed: AngularVelocity) -> Torque {
+		let voltage_ratio: Ratio = voltage.max(-MAX_VOLTAGE).min(MAX_VOLTAGE) / MAX_VOLTAGE;
+		let speed_ratio: Ratio = speed / self.free_speed();
+
+		(self.stall_torque() * (voltage_ratio - speed_ratio)).into()
+	}
+}
+
+/// Unpowered tracking wheel which turns a [`MockEncoder`] as the simulated robot moves
+pub struct SimulatedTrackingWheel {
+	encoder: MockEncoder,
+	wheel_radius: Length,
+	offset: Length,
+	orientation: WheelOrientation,
+}
+
+impl SimulatedTrackingWheel {
+	/// Creates a wheel which rolls forwards, mounted a signed distance to the right of the tracking centre
+	///
+	/// Offsets follow the same convention as [`TrackingWheel::parallel`](crate::odometry::TrackingWheel::parallel).
+	pub fn parallel(wheel_diameter: Length, offset: Length) -> Self {
+		Self::new(wheel_diameter, offset, WheelOrientation::Parallel)
+	}
+
+	/// Creates a wheel which rolls sideways, mounted a signed distance in front of the tracking centre
+	///
+	/// Offsets follow the same convention as
+	/// [`TrackingWheel::perpendicular`](crate::odometry::TrackingWheel::perpendicular).
+	pub fn perpendicular(wheel_diameter: Length, offset: Length) -> Self {
+		Self::new(wheel_diameter, offset, WheelOrientation::Perpendicular)
+	}
+
+	fn new(wheel_diameter: Length, offset: Length, orientation: WheelOrientation) -> Self {
+		Self {
+			encoder: MockEncoder::new(),
+			wheel_radius: wheel_diameter / 2.0,
+			offset,
+			orientation,
+		}
+	}
+
+	/// Turns the wheel by the amount it rolls while the robot moves by a small robot-relative displacement, with
+	/// rotation measured clockwise
+	fn roll(&self, forward: Length, sideways: Length, rotation: Angle) {
+		let distance: Length = match self.orientation {
+			WheelOrientation::Parallel => forward - rotation * self.offset,
+			WheelOrientation::Perpendicular => sideways + rotation * self.offset,
+		};
+
+		self.encoder.rotate((distance / self.wheel_radius).into());
+	}
+}
+
+/// State shared by every simulated chassis: where it is on the field, what it is carrying and what time it is
+#[derive(Default)]
+struct Body {
+	position: Position,
+	tracking_wheels: Vec<SimulatedTrackingWheel>,
+	inertial_sensors: Vec<MockInertialSensor>,
+	distance_sensors: Vec<(MockDistanceSensor, SensorMount)>,
+	field_map: FieldMap,
+	clock: ManualClock,
+}
+
+impl Body {
+	fn add_tracking_wheel(&mut self, wheel: SimulatedTrackingWheel) -> MockEncoder {
+		let encoder: MockEncoder = wheel.encoder.clone();
+		self.tracking_wheels.push(wheel);
+		encoder
+	}
+
+	fn add_inertial_sensor(&mut self) -> MockInertialSensor {
+		let sensor: MockInertialSensor = MockInertialSensor::new();
+		self.inertial_sensors.push(sensor.clone());
+		sensor
+	}
+
+	fn add_distance_sensor(&mut self, mount: SensorMount) -> MockDistanceSensor {
+		let sensor: MockDistanceSensor = MockDistanceSensor::new();
+		self.distance_sensors.push((sensor.clone(), mount));
+		self.measure_distances();
+		sensor
+	}
+
+	fn set_position(&mut self, position: Position) {
+		self.position = position;
+		self.measure_distances();
+	}
+
+	fn set_field_map(&mut self, field_map: FieldMap) {
+		self.field_map = field_map;
+		self.measure_distances();
+	}
+
+	/// Points every distance sensor at the field map from where the body now is
+	fn measure_distances(&self) {
+		for (sensor, mount) in self.distance_sensors.iter() {
+			let distance: Option<Length> = self
+				.field_map
+				.cast(mount.locate(self.position))
+				.filter(|distance| distance.value <= DISTANCE_SENSOR_RANGE_METERS);
+
+			sensor.set_distance(distance);
+		}
+	}
+
+	/// Moves the body by a small robot-relative displacement, using the heading halfway through the movement to
+	/// carry it onto the field
+	fn displace(&mut self, forward: Length, sideways: Length, rotation: Angle) {
+		let Position(coordinates, heading) = self.position;
+
+		let average_heading: Angle = heading + rotation / 2.0;
+		let sin: Ratio = average_heading.sin();
+		let cos: Ratio = average_heading.cos();
+
+		self.position = Position::new(
+			coordinates.x + forward * sin + sideways * cos,
+			coordinates.y + forward * cos - sideways * sin,
+			heading + rotation,
+		);
+
+		for wheel in self.tracking_wheels.iter() {
+			wheel.roll(forward, sideways, rotation);
+		}
+
+		for sensor in self.inertial_sensors.iter() {
+			sensor.rotate(rotation);
+		}
+
+		self.measure_distances();
+	}
+}
+
+/// Splits a step into substeps no longer than the physics is integrated over
+fn substeps(time: Time) -> impl Iterator<Item = Time> {
+	let count: usize = ceil((time / Time::new::<millisecond>(SUBSTEP_MILLISECONDS)).value).max(0.0) as usize;
+	let substep: Time = if count == 0 { Time::ZERO } else { time / count as f64 };
+
+	(0..count).map(move |_| substep)
+}
@@ -0,0 +1,218 @@
+use uom::{
+	si::{
+		acceleration::standard_gravity,
+		f64::{
+			Acceleration,
+			AngularAcceleration,
+			AngularVelocity,
+			Force,
+			Length,
+			Mass,
+			MomentOfInertia,
+			Ratio,
+			Time,
+			Torque,
+			Velocity,
+		},
+		ratio::ratio,
+	},
+	ConstZero,
+};
+
+use super::{substeps, Body, Cartridge, SimulatedTrackingWheel};
+use crate::{
+	clock::ManualClock,
+	coordinates::Position,
+	distance::{MockDistanceSensor, SensorMount},
+	encoder::MockEncoder,
+	field::FieldMap,
+	inertial::MockInertialSensor,
+	motor::MockMotor,
+	tank_drive::TankDrive,
+};
+
+/// Physical description of a tank drive chassis
+#[derive(Clone, Copy)]
+pub struct TankChassis {
+	/// Cartridge fitted to every drive motor
+	pub cartridge: Cartridge,
+
+	/// Ratio of wheel speed to motor speed, as in [`TankDrive::drive_ratio`]
+	pub drive_ratio: Ratio,
+	/// Diameter of the driven wheels
+	pub wheel_diameter: Length,
+	/// Distance between the centres of the left and right wheels
+	pub track_width: Length,
+
+	/// Mass of the whole robot
+	pub mass: Mass,
+	/// Resistance to turning about the centre of the chassis
+	pub moment_of_inertia: MomentOfInertia,
+	/// Coefficient of rolling resistance, the friction force on each side as a ratio of the weight it carries
+	pub rolling_resistance: Ratio,
+}
+
+impl TankChassis {
+	/// Describes the chassis a drive train is mounted on, taking the gearing and wheel geometry from the drive train
+	///
+	/// The moment of inertia is estimated by treating the robot as a uniform square plate as wide as the track, and
+	/// rolling resistance defaults to 5% of the robot's weight.
+	pub fn from_drive<M, const N: usize>(drive: &TankDrive<M, N>, cartridge: Cartridge, mass: Mass) -> Self {
+		Self {
+			cartridge,
+			drive_ratio: drive.drive_ratio,
+			wheel_diameter: drive.wheel_diameter,
+			track_width: drive.track_width,
+			mass,
+			moment_of_inertia: mass * drive.track_width * drive.track_width / 6.0,
+			rolling_resistance: Ratio::new::<ratio>(0.05),
+		}
+	}
+
+	fn wheel_radius(&self) -> Length { self.wheel_diameter / 2.0 }
+}
+
+/// Simulated tank drive which moves according to the voltages commanded of its motors
+///
+/// The motors handed out by [`left_motors`](Self::left_motors) and [`right_motors`](Self::right_motors) are meant to
+/// be given to a [`TankDrive`], after which every call to [`step`](Self::step) reads their voltages, integrates the
+/// chassis forward and writes back the resulting motor positions and velocities.
+pub struct TankSimulation<const N: usize> {
+	chassis: TankChassis,
+
+	left_motors: [MockMotor; N],
+	right_motors: [MockMotor; N],
+
+	velocity: Velocity,
+	angular_velocity: AngularVelocity,
+
+	body: Body,
+}
+
+impl<const N: usize> TankSimulation<N> {
+	/// Creates a simulation of a chassis sitting still at the origin
+	pub fn new(chassis: TankChassis) -> Self {
+		Self {
+			chassis,
+			left_motors: core::array::from_fn(|_| MockMotor::new()),
+			right_motors: core::array::from_fn(|_| MockMotor::new()),
+			velocity: Velocity::ZERO,
+			angular_velocity: AngularVelocity::ZERO,
+			body: Body::default(),
+		}
+	}
+
+	/// Gets handles to the motors on the left side of the chassis
+	pub fn left_motors(&self) -> [MockMotor; N] { self.left_motors.clone() }
+
+	/// Gets handles to the motors on the right side of the chassis
+	pub fn right_motors(&self) -> [MockMotor; N] { self.right_motors.clone() }
+
+	/// Gets a handle to the clock which is advanced as the simulation steps
+	pub fn clock(&self) -> ManualClock { self.body.clock.clone() }
+
+	/// Mounts a tracking wheel on the chassis, returning the encoder it turns
+	pub fn add_tracking_wheel(&mut self, wheel: SimulatedTrackingWheel) -> MockEncoder {
+		self.body.add_tracking_wheel(wheel)
+	}
+
+	/// Mounts an inertial sensor on the chassis which follows its true rotation
+	pub fn add_inertial_sensor(&mut self) -> MockInertialSensor { self.body.add_inertial_sensor() }
+
+	/// Mounts a distance sensor on the chassis which reads the distance to whatever it points at on the field map
+	pub fn add_distance_sensor(&mut self, mount: SensorMount) -> MockDistanceSensor {
+		self.body.add_distance_sensor(mount)
+	}
+
+	/// Replaces the field the distance sensors see, which starts out as the empty field
+	pub fn set_field_map(&mut self, field_map: FieldMap) { self.body.set_field_map(field_map); }
+
+	/// Gets the true position of the chassis on the field
+	pub fn get_position(&self) -> Position { self.body.position }
+
+	/// Moves the chassis to a position on the field without turning any wheels
+	pub fn set_position(&mut self, position: Position) { self.body.set_position(position); }
+
+	/// Gets the true forward velocity of the chassis
+	pub fn get_velocity(&self) -> Velocity { self.velocity }
+
+	/// Gets the true clockwise angular velocity of the chassis
+	pub fn get_angular_velocity(&self) -> AngularVelocity { self.angular_velocity }
+
+	/// Moves the simulation forward by a period of time
+	pub fn step(&mut self, time: Time) {
+		for delta_time in substeps(time) {
+			self.body.clock.advance(delta_time);
+			self.integrate(delta_time);
+		}
+	}
+
+	fn integrate(&mut self, delta_time: Time) {
+		let half_track: Length = self.chassis.track_width / 2.0;
+		let side_mass: Mass = self.chassis.mass / 2.0;
+		let side_friction: Force =
+			self.chassis.rolling_resistance * side_mass * Acceleration::new::<standard_gravity>(1.0);
+
+		let left_velocity: Velocity = self.velocity + self.angular_velocity * half_track;
+		let right_velocity: Velocity = self.velocity - self.angular_velocity * half_track;
+
+		let left_force: Force = self.side_force(&self.left_motors, left_velocity, side_mass, side_friction, delta_time);
+		let right_force: Force =
+			self.side_force(&self.right_motors, right_velocity, side_mass, side_friction, delta_time);
+
+		let acceleration: Acceleration = (left_force + right_force) / self.chassis.mass;
+		let angular_acceleration: AngularAcceleration =
+			((left_force - right_force) * half_track / self.chassis.moment_of_inertia).into();
+
+		self.velocity += acceleration * delta_time;
+		self.angular_velocity += (angular_acceleration * delta_time).into();
+
+		self.body.displace(
+			self.velocity * delta_time,
+			Length::ZERO,
+			(self.angular_velocity * delta_time).into(),
+		);
+
+		let left_motor_velocity: AngularVelocity =
+			self.motor_velocity(self.velocity + self.angular_velocity * half_track);
+		let right_motor_velocity: AngularVelocity =
+			self.motor_velocity(self.velocity - self.angular_velocity * half_track);
+
+		for (motors, velocity) in [
+			(&self.left_motors, left_motor_velocity),
+			(&self.right_motors, right_motor_velocity),
+		] {
+			for motor in motors.iter() {
+				motor.rotate((velocity * delta_time).into());
+				motor.set_actual_velocity(velocity);
+			}
+		}
+	}
+
+	/// Speed the motors on a side of the chassis turn at when the wheels on that side are rolling at a given speed
+	fn motor_velocity(&self, wheel_velocity: Velocity) -> AngularVelocity {
+		(wheel_velocity / self.chassis.wheel_radius() / self.chassis.drive_ratio).into()
+	}
+
+	/// Net force the ground pushes one side of the chassis with, after friction
+	///
+	/// Friction opposes the motors and the current motion together, up to its limit, so it can hold a side still
+	/// but never reverse it.
+	fn side_force(
+		&self, motors: &[MockMotor; N], velocity: Velocity, side_mass: Mass, side_friction: Force, delta_time: Time,
+	) -> Force {
+		let motor_velocity: AngularVelocity = self.motor_velocity(velocity);
+
+		let motor_torque: Torque = motors
+			.iter()
+			.map(|motor| self.chassis.cartridge.torque(motor.voltage(), motor_velocity))
+			.fold(Torque::ZERO, |total, torque| total + torque);
+
+		let drive_force: Force = motor_torque / self.chassis.drive_ratio / self.chassis.wheel_radius();
+
+		let stopping_force: Force = drive_force + side_mass * velocity / delta_time;
+		let friction: Force = -stopping_force.max(-side_friction).min(side_friction);
+
+		drive_force + friction
+	}
+}
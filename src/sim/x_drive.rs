@@ -0,0 +1,267 @@
+use core::f64::consts::FRAC_1_SQRT_2;
+
+use uom::{
+	si::{
+		acceleration::standard_gravity,
+		f64::{
+			Acceleration,
+			AngularAcceleration,
+			AngularVelocity,
+			Force,
+			Length,
+			Mass,
+			MomentOfInertia,
+			Ratio,
+			Time,
+			Torque,
+			Velocity,
+		},
+		ratio::ratio,
+	},
+	ConstZero,
+};
+
+use super::{substeps, Body, Cartridge, SimulatedTrackingWheel};
+use crate::{
+	clock::ManualClock,
+	coordinates::Position,
+	distance::{MockDistanceSensor, SensorMount},
+	encoder::MockEncoder,
+	field::FieldMap,
+	inertial::MockInertialSensor,
+	math::RealAngle,
+	motor::MockMotor,
+	x_drive::XDrive,
+};
+
+/// Physical description of an X-drive chassis, with four omni wheels at 45° to the frame
+#[derive(Clone, Copy)]
+pub struct XDriveChassis {
+	/// Cartridge fitted to every drive motor
+	pub cartridge: Cartridge,
+
+	/// Ratio of wheel speed to motor speed
+	pub drive_ratio: Ratio,
+	/// Diameter of the driven wheels
+	pub wheel_diameter: Length,
+	/// Distance from the centre of the chassis to the contact patch of each wheel
+	pub turn_radius: Length,
+
+	/// Mass of the whole robot
+	pub mass: Mass,
+	/// Resistance to turning about the centre of the chassis
+	pub moment_of_inertia: MomentOfInertia,
+	/// Coefficient of rolling resistance, the friction force on each wheel as a ratio of the weight it carries
+	pub rolling_resistance: Ratio,
+}
+
+impl XDriveChassis {
+	/// Describes the chassis a drive train is mounted on, taking the gearing and wheel geometry from the drive train
+	///
+	/// The moment of inertia is estimated by treating the robot as a uniform square plate with the wheels at its
+	/// corners, and rolling resistance defaults to 5% of the robot's weight.
+	pub fn from_drive<M>(drive: &XDrive<M>, cartridge: Cartridge, mass: Mass) -> Self {
+		Self {
+			cartridge,
+			drive_ratio: drive.drive_ratio,
+			wheel_diameter: drive.wheel_radius * 2.0,
+			turn_radius: drive.turn_radius,
+			mass,
+			moment_of_inertia: mass * drive.turn_radius * drive.turn_radius / 3.0,
+			rolling_resistance: Ratio::new::<ratio>(0.05),
+		}
+	}
+
+	fn wheel_radius(&self) -> Length { self.wheel_diameter / 2.0 }
+}
+
+/// Driven omni wheel, described in the robot frame with x to the right and y forwards
+struct Wheel {
+	motor: MockMotor,
+	x: Length,
+	y: Length,
+	direction_x: f64,
+	direction_y: f64,
+}
+
+impl Wheel {
+	/// Speed the wheel rolls at along its drive direction while the chassis moves with a robot-relative velocity and
+	/// a clockwise angular velocity
+	fn rolling_velocity(&self, sideways: Velocity, forward: Velocity, angular_velocity: AngularVelocity) -> Velocity {
+		let velocity_x: Velocity = sideways + angular_velocity * self.y;
+		let velocity_y: Velocity = forward - angular_velocity * self.x;
+
+		velocity_x * self.direction_x + velocity_y * self.direction_y
+	}
+}
+
+/// Simulated X-drive which moves according to the voltages commanded of its motors
+///
+/// The motors handed out by [`motors`](Self::motors) are meant to be given to an [`XDrive`], after which every call to
+/// [`step`](Self::step) reads their voltages, integrates the chassis forward and writes back the resulting motor
+/// positions and velocities. Tracking wheels turn as the chassis moves, so an
+/// [`OdometrySystem`](crate::odometry::OdometrySystem) can follow strafing and turning just as it would on the robot.
+pub struct XDriveSimulation {
+	chassis: XDriveChassis,
+
+	wheels: [Wheel; 4],
+
+	/// Velocity along the field x axis
+	velocity_x: Velocity,
+	/// Velocity along the field y axis
+	velocity_y: Velocity,
+	angular_velocity: AngularVelocity,
+
+	body: Body,
+}
+
+impl XDriveSimulation {
+	/// Creates a simulation of a chassis sitting still at the origin
+	pub fn new(chassis: XDriveChassis) -> Self {
+		let corner: Length = chassis.turn_radius * FRAC_1_SQRT_2;
+
+		let wheel = |x: Length, y: Length, direction_x: f64| Wheel {
+			motor: MockMotor::new(),
+			x,
+			y,
+			direction_x: direction_x * FRAC_1_SQRT_2,
+			direction_y: FRAC_1_SQRT_2,
+		};
+
+		Self {
+			chassis,
+			wheels: [
+				wheel(-corner, corner, 1.0),
+				wheel(-corner, -corner, -1.0),
+				wheel(corner, corner, -1.0),
+				wheel(corner, -corner, 1.0),
+			],
+			velocity_x: Velocity::ZERO,
+			velocity_y: Velocity::ZERO,
+			angular_velocity: AngularVelocity::ZERO,
+			body: Body::default(),
+		}
+	}
+
+	/// Gets handles to the front left, back left, front right and back right motors, in the order
+	/// [`XDrive::new`] takes them
+	pub fn motors(&self) -> [MockMotor; 4] { core::array::from_fn(|index| self.wheels[index].motor.clone()) }
+
+	/// Gets a handle to the clock which is advanced as the simulation steps
+	pub fn clock(&self) -> ManualClock { self.body.clock.clone() }
+
+	/// Mounts a tracking wheel on the chassis, returning the encoder it turns
+	pub fn add_tracking_wheel(&mut self, wheel: SimulatedTrackingWheel) -> MockEncoder {
+		self.body.add_tracking_wheel(wheel)
+	}
+
+	/// Mounts an inertial sensor on the chassis which follows its true rotation
+	pub fn add_inertial_sensor(&mut self) -> MockInertialSensor { self.body.add_inertial_sensor() }
+
+	/// Mounts a distance sensor on the chassis which reads the distance to whatever it points at on the field map
+	pub fn add_distance_sensor(&mut self, mount: SensorMount) -> MockDistanceSensor {
+		self.body.add_distance_sensor(mount)
+	}
+
+	/// Replaces the field the distance sensors see, which starts out as the empty field
+	pub fn set_field_map(&mut self, field_map: FieldMap) { self.body.set_field_map(field_map); }
+
+	/// Gets the true position of the chassis on the field
+	pub fn get_position(&self) -> Position { self.body.position }
+
+	/// Moves the chassis to a position on the field without turning any wheels
+	pub fn set_position(&mut self, position: Position) { self.body.set_position(position); }
+
+	/// Gets the true velocity of the chassis along the field x and y axes
+	pub fn get_velocity(&self) -> (Velocity, Velocity) { (self.velocity_x, self.velocity_y) }
+
+	/// Gets the true clockwise angular velocity of the chassis
+	pub fn get_angular_velocity(&self) -> AngularVelocity { self.angular_velocity }
+
+	/// Moves the simulation forward by a period of time
+	pub fn step(&mut self, time: Time) {
+		for delta_time in substeps(time) {
+			self.body.clock.advance(delta_time);
+			self.integrate(delta_time);
+		}
+	}
+
+	/// Velocity of the chassis relative to itself, as sideways (to the right) and forward components
+	fn local_velocity(&self) -> (Velocity, Velocity) {
+		let Position(_, heading) = self.body.position;
+		let sin: Ratio = heading.sin();
+		let cos: Ratio = heading.cos();
+
+		(
+			self.velocity_x * cos - self.velocity_y * sin,
+			self.velocity_x * sin + self.velocity_y * cos,
+		)
+	}
+
+	fn integrate(&mut self, delta_time: Time) {
+		let wheel_mass: Mass = self.chassis.mass / 4.0;
+		let wheel_friction: Force =
+			self.chassis.rolling_resistance * wheel_mass * Acceleration::new::<standard_gravity>(1.0);
+
+		let (sideways, forward): (Velocity, Velocity) = self.local_velocity();
+
+		let mut force_x: Force = Force::ZERO;
+		let mut force_y: Force = Force::ZERO;
+		let mut torque: Torque = Torque::ZERO;
+
+		for wheel in self.wheels.iter() {
+			let rolling_velocity: Velocity = wheel.rolling_velocity(sideways, forward, self.angular_velocity);
+			let motor_torque: Torque = self
+				.chassis
+				.cartridge
+				.torque(wheel.motor.voltage(), self.motor_velocity(rolling_velocity));
+
+			let drive_force: Force = motor_torque / self.chassis.drive_ratio / self.chassis.wheel_radius();
+
+			// Friction opposes the motor and the current motion together, up to its limit, so it can hold the wheel
+			// still but never reverse it
+			let stopping_force: Force = drive_force + wheel_mass * rolling_velocity / delta_time;
+			let force: Force = drive_force - stopping_force.max(-wheel_friction).min(wheel_friction);
+
+			let wheel_force_x: Force = force * wheel.direction_x;
+			let wheel_force_y: Force = force * wheel.direction_y;
+
+			force_x += wheel_force_x;
+			force_y += wheel_force_y;
+			torque += (wheel_force_x * wheel.y - wheel_force_y * wheel.x).into();
+		}
+
+		let Position(_, heading) = self.body.position;
+		let sin: Ratio = heading.sin();
+		let cos: Ratio = heading.cos();
+
+		let acceleration_x: Acceleration = (force_x * cos + force_y * sin) / self.chassis.mass;
+		let acceleration_y: Acceleration = (force_y * cos - force_x * sin) / self.chassis.mass;
+		let angular_acceleration: AngularAcceleration = (torque / self.chassis.moment_of_inertia).into();
+
+		self.velocity_x += acceleration_x * delta_time;
+		self.velocity_y += acceleration_y * delta_time;
+		self.angular_velocity += (angular_acceleration * delta_time).into();
+
+		let (sideways, forward): (Velocity, Velocity) = self.local_velocity();
+
+		self.body.displace(
+			forward * delta_time,
+			sideways * delta_time,
+			(self.angular_velocity * delta_time).into(),
+		);
+
+		for wheel in self.wheels.iter() {
+			let motor_velocity: AngularVelocity =
+				self.motor_velocity(wheel.rolling_velocity(sideways, forward, self.angular_velocity));
+
+			wheel.motor.rotate((motor_velocity * delta_time).into());
+			wheel.motor.set_actual_velocity(motor_velocity);
+		}
+	}
+
+	/// Speed a motor turns at when its wheel is rolling at a given speed
+	fn motor_velocity(&self, wheel_velocity: Velocity) -> AngularVelocity {
+		(wheel_velocity / self.chassis.wheel_radius() / self.chassis.drive_ratio).into()
+	}
+}
@@ -1,161 +1,194 @@
-use uom::{
-	si::f64::{Angle, AngularVelocity, ElectricPotential, Length, Ratio},
-	ConstZero,
-};
+use uom::si::f64::{Angle, AngularVelocity, ElectricPotential, Length, Ratio};
+#[cfg(feature = "vex-rt")]
+use uom::{si::f64::Time, ConstZero};
+#[cfg(feature = "vex-rt")]
 use vex_rt::{
-	prelude::{Motor, MotorError},
 	rtos::{Context, Loop},
 	select,
 };
 
+#[cfg(feature = "vex-rt")]
 use crate::{
-	pid::{PositionController, VelocityController},
-	Gains,
+	clock::{Clock, RtosClock},
+	motion_profile::{MotionProfile, Setpoint},
+	pid::{ControllerStatus, PositionController, VelocityController},
 	PID_CYCLE_DURATION,
 };
+use crate::{
+	motion_profile::Constraints,
+	motor::SmartMotor,
+	pid::{AntiWindup, PidGains, Settling},
+};
 
-pub struct TankDrive<const N: usize> {
-	pub left_motors: [Motor; N],
-	pub right_motors: [Motor; N],
+pub struct TankDrive<M, const N: usize> {
+	pub left_motors: [M; N],
+	pub right_motors: [M; N],
 
 	pub drive_ratio: Ratio,
 	pub wheel_diameter: Length,
 	pub track_width: Length,
 
-	pub distance_gains: Gains,
-	pub turn_gains: Gains,
+	pub distance_gains: PidGains<Angle, AngularVelocity>,
+	pub turn_gains: PidGains<Angle, AngularVelocity>,
+	/// Limits on the integral of the distance and turn controllers
+	pub anti_windup: AntiWindup<Angle, AngularVelocity>,
+	/// Conditions under which a move has finished, with the timeout counted from the end of the move's profile
+	pub settling: Settling<Angle>,
+	/// Limits on how quickly the robot drives in a straight line, which moves are profiled within
+	pub drive_constraints: Constraints<Length>,
+	/// Limits on how quickly the robot turns on the spot, which turns are profiled within
+	pub turn_constraints: Constraints<Angle>,
 
-	pub left_velocity_gains: Gains,
-	pub right_velocity_gains: Gains,
+	pub left_velocity_gains: PidGains<AngularVelocity, ElectricPotential>,
+	pub right_velocity_gains: PidGains<AngularVelocity, ElectricPotential>,
 
 	pub position_threshold: Angle,
 	pub velocity_threshold: AngularVelocity,
 }
 
-impl<const N: usize> TankDrive<N> {
+impl<M: SmartMotor, const N: usize> TankDrive<M, N> {
+	#[cfg(feature = "vex-rt")]
 	fn wheel_radius(&self) -> Length { return self.wheel_diameter / 2.0; }
 
 	/// Sets the drive train motor powers based on a left and right input,
-	pub fn drive_tank(&mut self, left: Ratio, right: Ratio) -> Result<(), MotorError> {
+	pub fn drive_tank(&mut self, left: Ratio, right: Ratio) -> Result<(), M::Error> {
 		self.drive_left(left)?;
 		self.drive_right(right)?;
 		Ok(())
 	}
 
 	/// Sets the drive train motor powers based on a horizontal and rotational input
-	pub fn drive_arcade(&mut self, x: Ratio, y: Ratio) -> Result<(), MotorError> {
+	pub fn drive_arcade(&mut self, x: Ratio, y: Ratio) -> Result<(), M::Error> {
 		self.drive_left(y + x)?;
 		self.drive_right(y - x)?;
 		Ok(())
 	}
 
-	fn drive_left(&mut self, value: Ratio) -> Result<(), MotorError> {
+	fn drive_left(&mut self, value: Ratio) -> Result<(), M::Error> {
 		for motor in self.left_motors.iter_mut() {
 			motor.move_ratio(value)?
 		}
 		Ok(())
 	}
 
-	fn drive_left_voltage(&mut self, voltage: ElectricPotential) -> Result<(), MotorError> {
+	#[cfg(feature = "vex-rt")]
+	fn drive_left_voltage(&mut self, voltage: ElectricPotential) -> Result<(), M::Error> {
 		for motor in self.left_motors.iter_mut() {
 			motor.move_voltage(voltage)?
 		}
 		Ok(())
 	}
 
-	fn drive_right(&mut self, value: Ratio) -> Result<(), MotorError> {
+	fn drive_right(&mut self, value: Ratio) -> Result<(), M::Error> {
 		for motor in self.right_motors.iter_mut() {
 			motor.move_ratio(value)?
 		}
 		Ok(())
 	}
 
-	fn drive_right_voltage(&mut self, voltage: ElectricPotential) -> Result<(), MotorError> {
+	#[cfg(feature = "vex-rt")]
+	fn drive_right_voltage(&mut self, voltage: ElectricPotential) -> Result<(), M::Error> {
 		for motor in self.right_motors.iter_mut() {
 			motor.move_voltage(voltage)?
 		}
 		Ok(())
 	}
 
-	fn tare_left_postition(&mut self) -> Result<(), MotorError> { self.left_motors[0].tare_position() }
-
-	fn tare_right_postition(&mut self) -> Result<(), MotorError> { self.right_motors[0].tare_position() }
-
-	fn get_left_position(&self) -> Result<Angle, MotorError> { self.left_motors[0].get_position() }
-
-	fn get_right_position(&self) -> Result<Angle, MotorError> { self.right_motors[0].get_position() }
-
-	fn get_left_velocity(&self) -> Result<AngularVelocity, MotorError> { self.left_motors[0].get_actual_velocity() }
-
-	fn get_right_velocity(&self) -> Result<AngularVelocity, MotorError> { self.right_motors[0].get_actual_velocity() }
-
-	/// Moves the drive train a specified relative distance
-	pub fn drive_distance(&mut self, distance: Length, ctx: &Context) -> Result<(), MotorError> {
-		self.tare_left_postition()?;
-		self.tare_right_postition()?;
-
-		let wheel_rotation_goal: Angle = (distance / self.wheel_radius()).into();
+	#[cfg(feature = "vex-rt")]
+	fn tare_left_postition(&mut self) -> Result<(), M::Error> { self.left_motors[0].tare_position() }
 
-		let motor_rotation_goal: Angle = (wheel_rotation_goal / self.drive_ratio).into();
+	#[cfg(feature = "vex-rt")]
+	fn tare_right_postition(&mut self) -> Result<(), M::Error> { self.right_motors[0].tare_position() }
 
-		let mut left_position_controller =
-			PositionController::new(motor_rotation_goal, self.distance_gains, self.position_threshold);
-		let mut right_position_controller =
-			PositionController::new(motor_rotation_goal, self.distance_gains, self.position_threshold);
+	#[cfg(feature = "vex-rt")]
+	fn get_left_position(&self) -> Result<Angle, M::Error> { self.left_motors[0].get_position() }
 
-		let mut left_speed_controller =
-			VelocityController::new(AngularVelocity::ZERO, self.left_velocity_gains, self.velocity_threshold);
-		let mut right_speed_controller = VelocityController::new(
-			AngularVelocity::ZERO,
-			self.right_velocity_gains,
-			self.velocity_threshold,
-		);
-
-		let mut pause = Loop::new(PID_CYCLE_DURATION);
+	#[cfg(feature = "vex-rt")]
+	fn get_right_position(&self) -> Result<Angle, M::Error> { self.right_motors[0].get_position() }
 
-		loop {
-			if left_position_controller.is_complete(self.get_left_position()?)
-				&& right_position_controller.is_complete(self.get_right_position()?)
-			{
-				self.drive_left(Ratio::ZERO)?;
-				self.drive_right(Ratio::ZERO)?;
-				break;
-			}
+	#[cfg(feature = "vex-rt")]
+	fn get_left_velocity(&self) -> Result<AngularVelocity, M::Error> { self.left_motors[0].get_actual_velocity() }
 
-			let left_motor_speed: AngularVelocity = left_position_controller.cycle(self.get_left_position()?);
-			let right_motor_speed: AngularVelocity = right_position_controller.cycle(self.get_right_position()?);
+	#[cfg(feature = "vex-rt")]
+	fn get_right_velocity(&self) -> Result<AngularVelocity, M::Error> { self.right_motors[0].get_actual_velocity() }
 
-			left_speed_controller.set_target(left_motor_speed);
-			right_speed_controller.set_target(right_motor_speed);
+	/// Moves the drive train a specified relative distance along a profile within the drive constraints, returning how
+	/// the move finished, or `None` if the context was cancelled first
+	#[cfg(feature = "vex-rt")]
+	pub fn drive_distance(&mut self, distance: Length, ctx: &Context) -> Result<Option<ControllerStatus>, M::Error> {
+		let profile: MotionProfile<Length> = MotionProfile::new(distance, self.drive_constraints);
 
-			let left_motor_voltage: ElectricPotential = left_speed_controller.cycle(self.get_left_velocity()?);
-			let right_motor_voltage: ElectricPotential = right_speed_controller.cycle(self.get_right_velocity()?);
+		let wheel_radius: Length = self.wheel_radius();
+		let drive_ratio: Ratio = self.drive_ratio;
 
-			self.drive_left_voltage(left_motor_voltage)?;
-			self.drive_right_voltage(right_motor_voltage)?;
+		self.follow_profile(
+			profile.duration(),
+			|time| {
+				let setpoint: Setpoint<Length> = profile.sample(time);
 
-			select! {
-				_ = ctx.done() => break,
-				_ = pause.select() => continue
-			}
-		}
+				(
+					(setpoint.position / wheel_radius / drive_ratio).into(),
+					(setpoint.velocity / wheel_radius / drive_ratio).into(),
+				)
+			},
+			1.0,
+			self.distance_gains,
+			ctx,
+		)
+	}
 
-		Ok(())
+	/// Rotates the drive train a specified relative angle along a profile within the turn constraints, returning how
+	/// the move finished, or `None` if the context was cancelled first
+	#[cfg(feature = "vex-rt")]
+	pub fn rotate_angle(&mut self, angle: Angle, ctx: &Context) -> Result<Option<ControllerStatus>, M::Error> {
+		let profile: MotionProfile<Angle> = MotionProfile::new(angle, self.turn_constraints);
+
+		let wheel_radius: Length = self.wheel_radius();
+		let half_track_width: Length = self.track_width * 0.5;
+		let drive_ratio: Ratio = self.drive_ratio;
+
+		self.follow_profile(
+			profile.duration(),
+			|time| {
+				let setpoint: Setpoint<Angle> = profile.sample(time);
+
+				(
+					(setpoint.position * half_track_width / wheel_radius / drive_ratio).into(),
+					(setpoint.velocity * half_track_width / wheel_radius / drive_ratio).into(),
+				)
+			},
+			-1.0,
+			self.turn_gains,
+			ctx,
+		)
 	}
 
-	/// Rotates the drive train a specified relative angle
-	pub fn rotate_angle(&mut self, angle: Angle, ctx: &Context) -> Result<(), MotorError> {
+	/// Drives the left motors along a profile of motor positions and velocities, and the right motors along the same
+	/// profile scaled by a direction, until both sides settle at the end of it
+	///
+	/// The position controllers' targets move along the profile each cycle with the profile's velocity added to their
+	/// output, so they only correct for how far the robot has fallen behind. The controllers can only settle once the
+	/// targets reach the end of the profile, and their timeout is stretched by the length of the profile so that it
+	/// only counts down once the targets stop, but they can stall along the way.
+	#[cfg(feature = "vex-rt")]
+	fn follow_profile(
+		&mut self, duration: Time, setpoint: impl Fn(Time) -> (Angle, AngularVelocity), right_direction: f64,
+		gains: PidGains<Angle, AngularVelocity>, ctx: &Context,
+	) -> Result<Option<ControllerStatus>, M::Error> {
 		self.tare_left_postition()?;
 		self.tare_right_postition()?;
 
-		let wheel_rotation_goal: Angle = ((angle * self.track_width * 0.5) / self.wheel_radius()).into();
-
-		let motor_rotation_goal: Angle = (wheel_rotation_goal / self.drive_ratio).into();
+		let settling: Settling<Angle> = Settling {
+			timeout: self.settling.timeout.map(|timeout| timeout + duration),
+			..self.settling
+		};
 
-		let mut left_position_controller =
-			PositionController::new(motor_rotation_goal, self.turn_gains, self.position_threshold);
-		let mut right_position_controller =
-			PositionController::new(-motor_rotation_goal, self.turn_gains, self.position_threshold);
+		let mut left_position_controller = PositionController::new(Angle::ZERO, gains, self.position_threshold)
+			.with_anti_windup(self.anti_windup)
+			.with_settling(settling);
+		let mut right_position_controller = PositionController::new(Angle::ZERO, gains, self.position_threshold)
+			.with_anti_windup(self.anti_windup)
+			.with_settling(settling);
 
 		let mut left_speed_controller =
 			VelocityController::new(AngularVelocity::ZERO, self.left_velocity_gains, self.velocity_threshold);
@@ -165,20 +198,36 @@ impl<const N: usize> TankDrive<N> {
 			self.velocity_threshold,
 		);
 
+		let start_time: Time = RtosClock.now();
+		let mut holding: bool = false;
+
 		let mut pause = Loop::new(PID_CYCLE_DURATION);
 
 		loop {
-			if left_position_controller.is_complete(self.get_left_position()?)
-				&& right_position_controller.is_complete(self.get_right_position()?)
-			{
+			let elapsed: Time = RtosClock.now() - start_time;
+			let (position, velocity): (Angle, AngularVelocity) = setpoint(elapsed.min(duration));
+
+			if !holding {
+				left_position_controller.track_target(position);
+				right_position_controller.track_target(position * right_direction);
+				holding = elapsed >= duration;
+			}
+
+			let left_motor_speed: AngularVelocity =
+				left_position_controller.cycle(self.get_left_position()?) + velocity;
+			let right_motor_speed: AngularVelocity =
+				right_position_controller.cycle(self.get_right_position()?) + velocity * right_direction;
+
+			let status: ControllerStatus = left_position_controller
+				.status()
+				.and(right_position_controller.status());
+
+			if status.is_finished() {
 				self.drive_left(Ratio::ZERO)?;
 				self.drive_right(Ratio::ZERO)?;
-				break;
+				return Ok(Some(status));
 			}
 
-			let left_motor_speed: AngularVelocity = left_position_controller.cycle(self.get_left_position()?);
-			let right_motor_speed: AngularVelocity = right_position_controller.cycle(self.get_right_position()?);
-
 			left_speed_controller.set_target(left_motor_speed);
 			right_speed_controller.set_target(right_motor_speed);
 
@@ -187,12 +236,13 @@ impl<const N: usize> TankDrive<N> {
 
 			self.drive_left_voltage(left_motor_voltage)?;
 			self.drive_right_voltage(right_motor_voltage)?;
+
 			select! {
 				_ = ctx.done() => break,
 				_ = pause.select() => continue
 			}
 		}
 
-		Ok(())
+		Ok(None)
 	}
 }
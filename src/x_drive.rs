@@ -1,22 +1,23 @@
 use uom::si::f64::{Length, Ratio};
-use vex_rt::prelude::{Motor, MotorError};
 
-pub struct XDrive {
-	front_left_motor: Motor,
-	back_left_motor: Motor,
-	front_right_motor: Motor,
-	back_right_motor: Motor,
+use crate::motor::SmartMotor;
 
-	drive_ratio: Ratio,
-	wheel_radius: Length,
+pub struct XDrive<M> {
+	front_left_motor: M,
+	back_left_motor: M,
+	front_right_motor: M,
+	back_right_motor: M,
 
-	turn_radius: Length,
+	pub(crate) drive_ratio: Ratio,
+	pub(crate) wheel_radius: Length,
+
+	pub(crate) turn_radius: Length,
 }
 
-impl XDrive {
+impl<M: SmartMotor> XDrive<M> {
 	pub fn new(
-		front_left_motor: Motor, back_left_motor: Motor, front_right_motor: Motor, back_right_motor: Motor,
-		drive_ratio: Ratio, wheel_diameter: Length, turn_diameter: Length,
+		front_left_motor: M, back_left_motor: M, front_right_motor: M, back_right_motor: M, drive_ratio: Ratio,
+		wheel_diameter: Length, turn_diameter: Length,
 	) -> Self {
 		Self {
 			front_left_motor,
@@ -29,7 +30,7 @@ impl XDrive {
 		}
 	}
 
-	pub fn drive(&mut self, x: Ratio, y: Ratio, rotation: Ratio) -> Result<(), MotorError> {
+	pub fn drive(&mut self, x: Ratio, y: Ratio, rotation: Ratio) -> Result<(), M::Error> {
 		self.front_left_motor.move_ratio(y + x + rotation)?;
 		self.front_right_motor.move_ratio(y - x - rotation)?;
 		self.back_left_motor.move_ratio(y - x + rotation)?;
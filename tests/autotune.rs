@@ -0,0 +1,206 @@
+//! Runs the relay autotunes against mechanisms with a known delay, timed by a manual clock, to check the ultimate
+//! gain and period they measure and the gains the tuning rules give from them
+
+use core::f64::consts::PI;
+use std::collections::VecDeque;
+
+use uom::si::{
+	angle::radian,
+	angular_velocity::radian_per_second,
+	electric_potential::volt,
+	f64::{Angle, AngularVelocity, ElectricPotential, Time},
+	time::{millisecond, second},
+};
+use vex_rs_lib::{
+	autotune::{PositionAutotune, TuningRule, UltimatePoint, VelocityAutotune},
+	clock::ManualClock,
+};
+
+const CYCLE_MILLISECONDS: f64 = 10.0;
+
+/// Number of cycles between the relay switching and the mechanism responding
+const DELAY_CYCLES: usize = 10;
+
+/// Mechanism whose position follows the velocity asked of it after a delay, along with the clock timing it
+struct DelayedIntegrator {
+	position: f64,
+	pending: VecDeque<f64>,
+	clock: ManualClock,
+}
+
+impl DelayedIntegrator {
+	fn new() -> Self {
+		Self {
+			position: 0.0,
+			pending: VecDeque::from(vec![0.0; DELAY_CYCLES]),
+			clock: ManualClock::new(),
+		}
+	}
+
+	/// Asks for a velocity in radians per second and moves on by a cycle
+	fn step(&mut self, velocity: f64) {
+		self.pending.push_back(velocity);
+		self.position += self.pending.pop_front().unwrap() * CYCLE_MILLISECONDS / 1000.0;
+		self.clock.advance(Time::new::<millisecond>(CYCLE_MILLISECONDS));
+	}
+}
+
+fn autotune_position(autotune: &mut PositionAutotune<ManualClock>, mechanism: &mut DelayedIntegrator) {
+	for _ in 0..10_000 {
+		if autotune.is_complete() {
+			return;
+		}
+
+		let velocity: AngularVelocity = autotune.cycle(Angle::new::<radian>(mechanism.position));
+		mechanism.step(velocity.get::<radian_per_second>());
+	}
+
+	panic!("the autotune never completed");
+}
+
+#[test]
+fn measures_the_oscillation_of_a_delayed_integrator() {
+	let mut mechanism: DelayedIntegrator = DelayedIntegrator::new();
+	let mut autotune: PositionAutotune<ManualClock> = PositionAutotune::with_clock(
+		Angle::new::<radian>(1.0),
+		AngularVelocity::new::<radian_per_second>(2.0),
+		mechanism.clock.clone(),
+	);
+
+	autotune_position(&mut autotune, &mut mechanism);
+
+	// Each half of the triangle wave overshoots for the whole delay, so the amplitude is the relay velocity times the
+	// delay and the period is four delays
+	let delay: f64 = DELAY_CYCLES as f64 * CYCLE_MILLISECONDS / 1000.0;
+	let amplitude: f64 = 2.0 * delay;
+
+	let point: UltimatePoint<Angle, AngularVelocity> = autotune.result().unwrap();
+	let gain: f64 = point.gain.value;
+	let expected_gain: f64 = 4.0 * 2.0 / (PI * amplitude);
+
+	assert!(
+		(gain - expected_gain).abs() < expected_gain * 0.1,
+		"measured an ultimate gain of {gain}, expected {expected_gain}"
+	);
+	assert!(
+		(point.period.get::<second>() - 4.0 * delay).abs() < 2.0 * CYCLE_MILLISECONDS / 1000.0,
+		"measured an ultimate period of {} s",
+		point.period.get::<second>()
+	);
+}
+
+#[test]
+fn takes_the_hysteresis_out_of_the_ultimate_gain() {
+	let mut mechanism: DelayedIntegrator = DelayedIntegrator::new();
+	let mut autotune: PositionAutotune<ManualClock> = PositionAutotune::with_clock(
+		Angle::new::<radian>(1.0),
+		AngularVelocity::new::<radian_per_second>(2.0),
+		mechanism.clock.clone(),
+	)
+	.with_hysteresis(Angle::new::<radian>(0.1));
+
+	autotune_position(&mut autotune, &mut mechanism);
+
+	// The relay now switches a hysteresis past the target, so the mechanism swings that much further and takes that
+	// much longer to come back, which the describing function of a relay with hysteresis allows for
+	let delay: f64 = DELAY_CYCLES as f64 * CYCLE_MILLISECONDS / 1000.0;
+	let amplitude: f64 = 2.0 * delay + 0.1;
+
+	let point: UltimatePoint<Angle, AngularVelocity> = autotune.result().unwrap();
+	let gain: f64 = point.gain.value;
+	let expected_gain: f64 = 4.0 * 2.0 / (PI * (amplitude * amplitude - 0.1 * 0.1).sqrt());
+
+	assert!(
+		(gain - expected_gain).abs() < expected_gain * 0.1,
+		"measured an ultimate gain of {gain}, expected {expected_gain}"
+	);
+	assert!(
+		(point.period.get::<second>() - (4.0 * delay + 4.0 * 0.1 / 2.0)).abs() < 2.0 * CYCLE_MILLISECONDS / 1000.0,
+		"measured an ultimate period of {} s",
+		point.period.get::<second>()
+	);
+}
+
+#[test]
+fn has_no_result_until_enough_oscillations_are_measured() {
+	let mut mechanism: DelayedIntegrator = DelayedIntegrator::new();
+	let mut autotune: PositionAutotune<ManualClock> = PositionAutotune::with_clock(
+		Angle::new::<radian>(1.0),
+		AngularVelocity::new::<radian_per_second>(2.0),
+		mechanism.clock.clone(),
+	)
+	.with_periods(2);
+
+	while !autotune.is_complete() {
+		assert!(autotune.result().is_none());
+
+		let velocity: AngularVelocity = autotune.cycle(Angle::new::<radian>(mechanism.position));
+		mechanism.step(velocity.get::<radian_per_second>());
+	}
+
+	assert!(autotune.result().is_some());
+	assert_eq!(
+		autotune.cycle(Angle::new::<radian>(mechanism.position)),
+		AngularVelocity::new::<radian_per_second>(0.0)
+	);
+}
+
+#[test]
+fn switches_the_voltage_around_the_bias() {
+	let mut autotune: VelocityAutotune<ManualClock> = VelocityAutotune::with_clock(
+		AngularVelocity::new::<radian_per_second>(10.0),
+		ElectricPotential::new::<volt>(2.0),
+		ManualClock::new(),
+	)
+	.with_bias(ElectricPotential::new::<volt>(6.0));
+
+	assert_eq!(
+		autotune.cycle(AngularVelocity::new::<radian_per_second>(5.0)),
+		ElectricPotential::new::<volt>(8.0)
+	);
+	assert_eq!(
+		autotune.cycle(AngularVelocity::new::<radian_per_second>(15.0)),
+		ElectricPotential::new::<volt>(4.0)
+	);
+}
+
+#[test]
+fn measures_a_velocity_loop_in_volts() {
+	// Motor whose velocity follows the voltage after a delay, at 1 radian per second per volt
+	let mut mechanism: DelayedIntegrator = DelayedIntegrator::new();
+	let mut autotune: VelocityAutotune<ManualClock> = VelocityAutotune::with_clock(
+		AngularVelocity::new::<radian_per_second>(0.0),
+		ElectricPotential::new::<volt>(3.0),
+		mechanism.clock.clone(),
+	);
+
+	while !autotune.is_complete() {
+		let voltage: ElectricPotential = autotune.cycle(AngularVelocity::new::<radian_per_second>(mechanism.position));
+		mechanism.step(voltage.get::<volt>());
+	}
+
+	let delay: f64 = DELAY_CYCLES as f64 * CYCLE_MILLISECONDS / 1000.0;
+	let expected_gain: f64 = 4.0 * 3.0 / (PI * 3.0 * delay);
+
+	let point: UltimatePoint<AngularVelocity, ElectricPotential> = autotune.result().unwrap();
+	assert!((point.gain.value - expected_gain).abs() < expected_gain * 0.1);
+}
+
+#[test]
+fn turns_the_ultimate_point_into_gains() {
+	let point: UltimatePoint<Angle, AngularVelocity> = UltimatePoint {
+		gain: AngularVelocity::new::<radian_per_second>(10.0) / Angle::new::<radian>(1.0),
+		period: Time::new::<second>(0.8),
+	};
+
+	let ziegler_nichols = point.gains(TuningRule::ZieglerNichols);
+	assert!((ziegler_nichols.proportional.value - 6.0).abs() < 1e-9);
+	assert!((ziegler_nichols.integral.value - 6.0 / 0.4).abs() < 1e-9);
+	assert!((ziegler_nichols.derivative.value - 6.0 * 0.1).abs() < 1e-9);
+
+	let tyreus_luyben = point.gains(TuningRule::TyreusLuyben);
+	let proportional: f64 = 10.0 / 2.2;
+	assert!((tyreus_luyben.proportional.value - proportional).abs() < 1e-9);
+	assert!((tyreus_luyben.integral.value - proportional / (0.8 * 2.2)).abs() < 1e-9);
+	assert!((tyreus_luyben.derivative.value - proportional * 0.8 / 6.3).abs() < 1e-9);
+}
@@ -0,0 +1,236 @@
+//! Checks that the calibrator solves for the wheel diameters and offsets from runs over known distances and
+//! rotations, and refuses runs it cannot solve from
+
+use core::f64::consts::PI;
+
+use uom::si::{
+	angle::radian,
+	f64::{Angle, Length},
+	length::inch,
+};
+use vex_rs_lib::{
+	coordinates::Position,
+	encoder::MockEncoder,
+	odometry::{Calibration, CalibrationError, Calibrator, OdometrySystem, TrackingLayout, TrackingWheel},
+};
+
+/// Diameter the wheels were measured at with a ruler
+const MEASURED_DIAMETER: f64 = 2.0;
+/// Diameter the wheels actually roll at
+const TRUE_DIAMETER: f64 = 2.1;
+
+/// Offsets the wheels actually sit at, left, right and rear
+const TRUE_OFFSETS: [f64; 3] = [-5.5, 5.0, -3.0];
+
+fn inches(value: f64) -> Length { Length::new::<inch>(value) }
+
+/// Three-wheel robot whose encoders the test rolls by hand, with a layout measured by ruler
+struct Rig {
+	left: MockEncoder,
+	right: MockEncoder,
+	rear: MockEncoder,
+}
+
+impl Rig {
+	fn new() -> Self {
+		Self {
+			left: MockEncoder::new(),
+			right: MockEncoder::new(),
+			rear: MockEncoder::new(),
+		}
+	}
+
+	fn layout(&self) -> TrackingLayout<MockEncoder> {
+		TrackingLayout::three_wheel(
+			self.left.clone(),
+			self.right.clone(),
+			self.rear.clone(),
+			inches(MEASURED_DIAMETER),
+			inches(5.0),
+			inches(5.0),
+			inches(3.0),
+		)
+	}
+
+	/// Rolls each wheel over a distance in inches
+	fn roll(&self, left: f64, right: f64, rear: f64) {
+		let radius: f64 = TRUE_DIAMETER / 2.0;
+
+		self.left.rotate(Angle::new::<radian>(left / radius));
+		self.right.rotate(Angle::new::<radian>(right / radius));
+		self.rear.rotate(Angle::new::<radian>(rear / radius));
+	}
+
+	/// Spins the robot clockwise on the spot by a number of turns
+	fn spin(&self, spins: f64) {
+		let rotation: f64 = spins * 2.0 * PI;
+		let [left, right, rear] = TRUE_OFFSETS;
+
+		// Parallel wheels roll backwards on the right of the centre, and perpendicular wheels roll right behind it
+		self.roll(-left * rotation, -right * rotation, rear * rotation);
+	}
+}
+
+fn assert_near(actual: Length, expected: f64) {
+	let actual: f64 = actual.get::<inch>();
+	assert!(
+		(actual - expected).abs() < 1e-9,
+		"expected {expected} in, got {actual} in"
+	);
+}
+
+fn calibrate(rig: &Rig) -> Result<Calibration, CalibrationError> {
+	let mut calibrator: Calibrator<MockEncoder> = Calibrator::new(rig.layout());
+
+	calibrator.start().unwrap();
+	rig.roll(48.0, 48.0, 0.0);
+	calibrator.finish_straight(inches(48.0)).unwrap();
+
+	calibrator.start().unwrap();
+	rig.spin(3.0);
+	calibrator.finish_spins(3.0).unwrap();
+
+	calibrator.solve()
+}
+
+#[test]
+fn solves_for_the_diameters_and_offsets() {
+	let rig: Rig = Rig::new();
+	let calibration: Calibration = calibrate(&rig).unwrap();
+
+	assert_eq!(calibration.wheels.len(), 3);
+
+	for (wheel, offset) in calibration.wheels.iter().zip(TRUE_OFFSETS) {
+		assert_near(wheel.diameter, TRUE_DIAMETER);
+		assert_near(wheel.offset, offset);
+	}
+}
+
+#[test]
+fn measures_perpendicular_wheels_from_a_strafe() {
+	let rig: Rig = Rig::new();
+	let mut calibrator: Calibrator<MockEncoder> = Calibrator::new(rig.layout());
+
+	calibrator.start().unwrap();
+	rig.roll(24.0, 24.0, 0.0);
+	calibrator.finish_straight(inches(24.0)).unwrap();
+
+	// The rear wheel slips and covers less ground than the parallel wheels would suggest
+	calibrator.start().unwrap();
+	rig.roll(0.0, 0.0, 23.0);
+	calibrator.finish_strafe(inches(24.0)).unwrap();
+
+	calibrator.start().unwrap();
+	rig.spin(-2.0);
+	calibrator.finish_spins(-2.0).unwrap();
+
+	let calibration: Calibration = calibrator.solve().unwrap();
+	let rear_diameter: f64 = TRUE_DIAMETER * 24.0 / 23.0;
+
+	assert_near(calibration.wheels[0].diameter, TRUE_DIAMETER);
+	assert_near(calibration.wheels[2].diameter, rear_diameter);
+	assert_near(
+		calibration.wheels[2].offset,
+		TRUE_OFFSETS[2] * rear_diameter / TRUE_DIAMETER,
+	);
+}
+
+#[test]
+fn needs_both_runs() {
+	let rig: Rig = Rig::new();
+	let mut calibrator: Calibrator<MockEncoder> = Calibrator::new(rig.layout());
+
+	assert_eq!(calibrator.solve().unwrap_err(), CalibrationError::StraightNotRecorded);
+
+	calibrator.start().unwrap();
+	rig.roll(24.0, 24.0, 0.0);
+	calibrator.finish_straight(inches(24.0)).unwrap();
+
+	assert_eq!(calibrator.solve().unwrap_err(), CalibrationError::SpinsNotRecorded);
+}
+
+#[test]
+fn refuses_spins_too_small_to_solve_from() {
+	let rig: Rig = Rig::new();
+	let mut calibrator: Calibrator<MockEncoder> = Calibrator::new(rig.layout());
+
+	calibrator.start().unwrap();
+	rig.roll(24.0, 24.0, 0.0);
+	calibrator.finish_straight(inches(24.0)).unwrap();
+
+	calibrator.start().unwrap();
+	rig.spin(0.01);
+	calibrator.finish_spins(0.01).unwrap();
+
+	assert_eq!(calibrator.solve().unwrap_err(), CalibrationError::SpinsTooSmall);
+
+	calibrator.finish_spins(0.0).unwrap();
+	assert_eq!(calibrator.solve().unwrap_err(), CalibrationError::SpinsTooSmall);
+}
+
+#[test]
+fn refuses_wheels_which_did_not_turn_or_turned_backwards() {
+	let rig: Rig = Rig::new();
+	let mut calibrator: Calibrator<MockEncoder> = Calibrator::new(rig.layout());
+
+	calibrator.start().unwrap();
+	rig.roll(24.0, 0.0, 0.0);
+	calibrator.finish_straight(inches(24.0)).unwrap();
+
+	calibrator.start().unwrap();
+	rig.spin(1.0);
+	calibrator.finish_spins(1.0).unwrap();
+
+	assert_eq!(calibrator.solve().unwrap_err(), CalibrationError::WheelDidNotTurn(1));
+
+	calibrator.start().unwrap();
+	rig.roll(24.0, -24.0, 0.0);
+	calibrator.finish_straight(inches(24.0)).unwrap();
+
+	assert_eq!(calibrator.solve().unwrap_err(), CalibrationError::WheelReversed(1));
+}
+
+#[test]
+fn applies_to_the_layout() {
+	let rig: Rig = Rig::new();
+	let calibration: Calibration = calibrate(&rig).unwrap();
+
+	let mut layout: TrackingLayout<MockEncoder> = rig.layout();
+	calibration.apply(&mut layout);
+
+	let mut odometry = OdometrySystem::new(layout).unwrap();
+
+	rig.roll(10.0, 10.0, 0.0);
+	odometry.cycle().unwrap();
+	rig.spin(0.25);
+	odometry.cycle().unwrap();
+
+	let Position(coordinates, heading) = odometry.get_position();
+	assert_near(coordinates.x, 0.0);
+	assert_near(coordinates.y, 10.0);
+	assert!((heading.get::<radian>() - PI / 2.0).abs() < 1e-9);
+}
+
+#[test]
+fn displays_as_the_calibrated_layout() {
+	let rig: Rig = Rig::new();
+	let mut calibrator: Calibrator<MockEncoder> = Calibrator::new(TrackingLayout::new(vec![
+		TrackingWheel::parallel(rig.left.clone(), inches(MEASURED_DIAMETER), inches(-5.0)),
+		TrackingWheel::parallel(rig.right.clone(), inches(MEASURED_DIAMETER), inches(5.0)),
+	]));
+
+	calibrator.start().unwrap();
+	rig.roll(48.0, 48.0, 0.0);
+	calibrator.finish_straight(inches(48.0)).unwrap();
+
+	calibrator.start().unwrap();
+	rig.spin(3.0);
+	calibrator.finish_spins(3.0).unwrap();
+
+	assert_eq!(
+		calibrator.solve().unwrap().to_string(),
+		"TrackingLayout::new(vec![\n\tTrackingWheel::parallel(encoder_0, Length::new::<inch>(2.1000), \
+		 Length::new::<inch>(-5.5000)),\n\tTrackingWheel::parallel(encoder_1, Length::new::<inch>(2.1000), \
+		 Length::new::<inch>(5.0000)),\n])"
+	);
+}
@@ -0,0 +1,117 @@
+//! Checks how the uncertainty in the odometry position grows as the robot drives and is cleared when the position is
+//! set
+
+use uom::si::{
+	angle::{degree, radian},
+	f64::{Angle, Length},
+	length::inch,
+};
+use vex_rs_lib::{
+	coordinates::Position,
+	encoder::MockEncoder,
+	inertial::MockInertialSensor,
+	odometry::{HeadingFusion, OdometrySystem, PoseCovariance, TrackingLayout},
+};
+
+const WHEEL_DIAMETER_INCHES: f64 = 2.0;
+const OFFSET_INCHES: f64 = 5.0;
+
+fn inches(value: f64) -> Length { Length::new::<inch>(value) }
+
+fn layout(left: &MockEncoder, right: &MockEncoder) -> TrackingLayout<MockEncoder> {
+	TrackingLayout::two_wheel(
+		left.clone(),
+		right.clone(),
+		inches(WHEEL_DIAMETER_INCHES),
+		inches(OFFSET_INCHES),
+		inches(OFFSET_INCHES),
+	)
+}
+
+/// Rolls both wheels forwards by an inch
+fn roll(left: &MockEncoder, right: &MockEncoder) {
+	left.rotate(Angle::new::<radian>(1.0));
+	right.rotate(Angle::new::<radian>(1.0));
+}
+
+#[test]
+fn grows_as_the_robot_drives() {
+	let left: MockEncoder = MockEncoder::new();
+	let right: MockEncoder = MockEncoder::new();
+	let mut odometry = OdometrySystem::new(layout(&left, &right)).unwrap();
+
+	assert_eq!(odometry.get_covariance(), PoseCovariance::default());
+
+	let mut previous: PoseCovariance = odometry.get_covariance();
+
+	for _ in 0..10 {
+		roll(&left, &right);
+		odometry.cycle().unwrap();
+
+		let covariance: PoseCovariance = odometry.get_covariance();
+		assert!(covariance.y_deviation() > previous.y_deviation());
+		assert!(covariance.heading_deviation() > previous.heading_deviation());
+		previous = covariance;
+	}
+}
+
+#[test]
+fn spreads_across_the_direction_of_travel_as_the_heading_grows_uncertain() {
+	let left: MockEncoder = MockEncoder::new();
+	let right: MockEncoder = MockEncoder::new();
+	let mut odometry = OdometrySystem::new(layout(&left, &right)).unwrap();
+
+	roll(&left, &right);
+	odometry.cycle().unwrap();
+	let early: Length = odometry.get_covariance().x_deviation();
+
+	for _ in 0..30 {
+		roll(&left, &right);
+		odometry.cycle().unwrap();
+	}
+
+	let late: PoseCovariance = odometry.get_covariance();
+
+	// Driving along y, so only the heading error can move the robot along x, and it compounds with distance
+	assert!(late.x_deviation() > early * 10.0);
+	assert!(late.x_deviation() > late.y_deviation());
+}
+
+#[test]
+fn is_cleared_when_the_position_is_set() {
+	let left: MockEncoder = MockEncoder::new();
+	let right: MockEncoder = MockEncoder::new();
+	let mut odometry = OdometrySystem::new(layout(&left, &right)).unwrap();
+
+	for _ in 0..10 {
+		roll(&left, &right);
+		odometry.cycle().unwrap();
+	}
+
+	odometry.set_y(inches(24.0));
+	let covariance: PoseCovariance = odometry.get_covariance();
+	assert_eq!(covariance.0[1], [0.0; 3]);
+	assert!(covariance.x_deviation() > Length::new::<inch>(0.0));
+
+	odometry.set_position(Position::new(inches(24.0), inches(24.0), Angle::new::<degree>(90.0)));
+	assert_eq!(odometry.get_covariance(), PoseCovariance::default());
+}
+
+#[test]
+fn takes_an_inertial_heading_as_exact() {
+	let left: MockEncoder = MockEncoder::new();
+	let right: MockEncoder = MockEncoder::new();
+	let mut odometry =
+		OdometrySystem::new_with_inertial(layout(&left, &right), MockInertialSensor::new(), HeadingFusion::Replace)
+			.unwrap();
+
+	for _ in 0..10 {
+		roll(&left, &right);
+		odometry.cycle().unwrap();
+	}
+
+	let covariance: PoseCovariance = odometry.get_covariance();
+	assert_eq!(covariance.heading_deviation(), Angle::new::<radian>(0.0));
+	assert!(covariance.y_deviation() > Length::new::<inch>(0.0));
+	assert_eq!(covariance.x_deviation(), Length::new::<inch>(0.0));
+}
@@ -0,0 +1,63 @@
+//! Checks that odometry keeps every movement of the wheels when one of its encoders fails to read
+
+use uom::si::{
+	angle::radian,
+	f64::{Angle, Length},
+	length::inch,
+};
+use vex_rs_lib::{
+	coordinates::Position,
+	encoder::MotorEncoder,
+	motor::{MockMotor, MockMotorError},
+	odometry::{OdometryError, OdometrySystem, TrackingLayout},
+};
+
+const WHEEL_DIAMETER_INCHES: f64 = 2.75;
+const OFFSET_INCHES: f64 = 5.0;
+
+/// Rolls both wheels forwards by a distance in inches
+fn roll(left: &MockMotor, right: &MockMotor, inches: f64) {
+	let angle: Angle = Angle::new::<radian>(inches / (WHEEL_DIAMETER_INCHES / 2.0));
+	left.rotate(angle);
+	right.rotate(angle);
+}
+
+#[test]
+fn movement_is_kept_through_a_failed_encoder_read() {
+	let left: MockMotor = MockMotor::new();
+	let right: MockMotor = MockMotor::new();
+
+	let offset: Length = Length::new::<inch>(OFFSET_INCHES);
+	let mut odometry = OdometrySystem::new(TrackingLayout::two_wheel(
+		MotorEncoder::new(left.clone()),
+		MotorEncoder::new(right.clone()),
+		Length::new::<inch>(WHEEL_DIAMETER_INCHES),
+		offset,
+		offset,
+	))
+	.unwrap();
+
+	roll(&left, &right, 10.0);
+	odometry.cycle().unwrap();
+
+	// Only the second wheel fails, so the first is read before the failure is found
+	roll(&left, &right, 6.0);
+	right.set_disconnected(true);
+	assert!(matches!(
+		odometry.cycle(),
+		Err(OdometryError::Encoder(MockMotorError::Disconnected))
+	));
+
+	right.set_disconnected(false);
+	odometry.cycle().unwrap();
+
+	let Position(coordinates, heading) = odometry.get_position();
+	let y: f64 = coordinates.y.get::<inch>();
+	let heading: f64 = heading.get::<radian>();
+
+	assert!((y - 16.0).abs() < 1e-9, "odometry is at {y} in after driving 16 in");
+	assert!(
+		heading.abs() < 1e-9,
+		"odometry turned {heading} rad while driving straight"
+	);
+}
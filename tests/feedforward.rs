@@ -0,0 +1,159 @@
+//! Fits the feedforward model to logs of a simulated motor with known constants, and checks the logs it refuses to
+//! fit
+
+use uom::si::{
+	angular_acceleration::radian_per_second_squared,
+	angular_velocity::radian_per_second,
+	electric_potential::volt,
+	f64::{AngularAcceleration, AngularVelocity, ElectricPotential, Time},
+	time::second,
+};
+use vex_rs_lib::feedforward::{Characterisation, MotorFeedforward};
+
+const CYCLE_MILLISECONDS: f64 = 10.0;
+
+/// Constants of the simulated motor, in volts, volts per radian per second and volts per radian per second squared
+const STATIC_VOLTAGE: f64 = 0.8;
+const VELOCITY_GAIN: f64 = 0.5;
+const ACCELERATION_GAIN: f64 = 0.1;
+
+/// Motor which moves exactly as the model says, logged as it is driven
+struct Rig {
+	time: f64,
+	velocity: f64,
+	log: Characterisation,
+}
+
+impl Rig {
+	fn new() -> Self {
+		Self {
+			time: 0.0,
+			velocity: 0.0,
+			log: Characterisation::new(),
+		}
+	}
+
+	/// Drives the motor for a number of cycles with a voltage which changes with the cycle, logging each one if asked
+	fn drive(&mut self, cycles: usize, voltage: impl Fn(usize) -> f64, record: bool) {
+		let delta_time: f64 = CYCLE_MILLISECONDS / 1000.0;
+
+		for cycle in 0..cycles {
+			let voltage: f64 = voltage(cycle);
+
+			if record {
+				self.log.record(
+					Time::new::<second>(self.time),
+					ElectricPotential::new::<volt>(voltage),
+					AngularVelocity::new::<radian_per_second>(self.velocity),
+				);
+			}
+
+			// Static friction holds the motor still until the voltage overcomes it
+			let friction: f64 = if self.velocity != 0.0 {
+				STATIC_VOLTAGE * self.velocity.signum()
+			} else if voltage.abs() > STATIC_VOLTAGE {
+				STATIC_VOLTAGE * voltage.signum()
+			} else {
+				voltage
+			};
+
+			// Integrated exactly over the cycle, so the velocity follows the model between samples as well as at them
+			let settled: f64 = (voltage - friction) / VELOCITY_GAIN;
+			let decay: f64 = (-delta_time * VELOCITY_GAIN / ACCELERATION_GAIN).exp();
+			let velocity: f64 = settled + (self.velocity - settled) * decay;
+
+			self.velocity = if self.velocity != 0.0 && velocity.signum() != self.velocity.signum() {
+				0.0
+			} else {
+				velocity
+			};
+			self.time += delta_time;
+		}
+	}
+
+	/// Ramps slowly up to 8 V, rests, then steps to -6 V, as a characterisation run does
+	fn characterise(&mut self) {
+		self.drive(300, |cycle| 8.0 * cycle as f64 / 300.0, true);
+		self.drive(100, |_| 0.0, false);
+		self.drive(100, |_| -6.0, true);
+	}
+}
+
+fn assert_within(actual: f64, expected: f64, tolerance: f64) {
+	assert!(
+		(actual - expected).abs() < expected.abs() * tolerance,
+		"expected {expected}, got {actual}"
+	);
+}
+
+#[test]
+fn fits_the_constants_of_the_motor() {
+	let mut rig: Rig = Rig::new();
+	rig.characterise();
+
+	let model: MotorFeedforward = rig.log.fit().unwrap();
+
+	assert_within(model.static_voltage.get::<volt>(), STATIC_VOLTAGE, 0.05);
+	assert_within(model.velocity_gain.value, VELOCITY_GAIN, 0.05);
+	assert_within(model.acceleration_gain.value, ACCELERATION_GAIN, 0.1);
+}
+
+#[test]
+fn ignores_the_gap_between_phases() {
+	let mut rig: Rig = Rig::new();
+	rig.characterise();
+
+	// Resting for longer moves the samples either side of the gap further apart without changing the fit
+	let mut rested: Rig = Rig::new();
+	rested.drive(300, |cycle| 8.0 * cycle as f64 / 300.0, true);
+	rested.drive(300, |_| 0.0, false);
+	rested.drive(100, |_| -6.0, true);
+
+	let model: MotorFeedforward = rig.log.fit().unwrap();
+	let rested: MotorFeedforward = rested.log.fit().unwrap();
+
+	assert_within(rested.acceleration_gain.value, model.acceleration_gain.value, 1e-6);
+}
+
+#[test]
+fn needs_sudden_changes_in_voltage_to_tell_the_constants_apart() {
+	let mut rig: Rig = Rig::new();
+
+	// Held at one voltage, friction, back EMF and inertia cannot be told apart
+	rig.drive(50, |_| 0.0, true);
+	rig.drive(1000, |_| 6.0, false);
+	rig.drive(100, |_| 6.0, true);
+
+	assert!(rig.log.fit().is_none());
+}
+
+#[test]
+fn needs_the_motor_to_move() {
+	let mut rig: Rig = Rig::new();
+	rig.drive(100, |_| 0.5, true);
+
+	assert!(rig.log.fit().is_none());
+	assert!(Characterisation::new().fit().is_none());
+}
+
+#[test]
+fn gives_the_voltage_for_a_velocity_and_acceleration() {
+	let model: MotorFeedforward = MotorFeedforward::new(
+		ElectricPotential::new::<volt>(STATIC_VOLTAGE),
+		ElectricPotential::new::<volt>(VELOCITY_GAIN) / AngularVelocity::new::<radian_per_second>(1.0),
+		ElectricPotential::new::<volt>(ACCELERATION_GAIN) / AngularAcceleration::new::<radian_per_second_squared>(1.0),
+	);
+
+	let voltage = |velocity: f64, acceleration: f64| {
+		model
+			.calculate(
+				AngularVelocity::new::<radian_per_second>(velocity),
+				AngularAcceleration::new::<radian_per_second_squared>(acceleration),
+			)
+			.get::<volt>()
+	};
+
+	assert!((voltage(10.0, 5.0) - (0.8 + 5.0 + 0.5)).abs() < 1e-9);
+	assert!((voltage(-10.0, 0.0) - (-0.8 - 5.0)).abs() < 1e-9);
+	assert!(voltage(0.0, 0.0).abs() < 1e-9);
+}
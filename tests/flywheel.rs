@@ -0,0 +1,228 @@
+//! Runs the flywheel controllers against a simulated flywheel, timed by a manual clock, and checks the recovery
+//! metrics measure the shots they are shown
+
+use uom::si::{
+	angle::radian,
+	angular_velocity::radian_per_second,
+	electric_potential::volt,
+	f64::{Angle, AngularVelocity, ElectricPotential, Time},
+	time::{millisecond, second},
+};
+use vex_rs_lib::{
+	clock::ManualClock,
+	flywheel::{BangBang, Recovery, RecoveryMetrics, TakeBackHalf},
+	pid::VelocityControl,
+};
+
+const CYCLE_MILLISECONDS: f64 = 10.0;
+
+/// Velocity the simulated flywheel settles at per volt, in radians per second
+const SPEED_PER_VOLT: f64 = 40.0;
+/// Time constant of the simulated flywheel, in seconds
+const TIME_CONSTANT: f64 = 0.5;
+
+const TARGET: f64 = 300.0;
+
+fn radians_per_second(value: f64) -> AngularVelocity { AngularVelocity::new::<radian_per_second>(value) }
+
+fn volts(value: f64) -> ElectricPotential { ElectricPotential::new::<volt>(value) }
+
+/// Flywheel whose speed approaches the speed its voltage holds, along with the clock timing it
+struct Flywheel {
+	velocity: f64,
+	clock: ManualClock,
+}
+
+impl Flywheel {
+	fn new() -> Self {
+		Self {
+			velocity: 0.0,
+			clock: ManualClock::new(),
+		}
+	}
+
+	/// Runs a controller for a number of cycles, returning the last voltage it applied
+	fn run(&mut self, controller: &mut impl VelocityControl, cycles: usize) -> f64 {
+		let delta_time: f64 = CYCLE_MILLISECONDS / 1000.0;
+		let mut voltage: f64 = 0.0;
+
+		for _ in 0..cycles {
+			self.clock.advance(Time::new::<millisecond>(CYCLE_MILLISECONDS));
+			voltage = controller.cycle(radians_per_second(self.velocity)).get::<volt>();
+			self.velocity += (voltage * SPEED_PER_VOLT - self.velocity) * delta_time / TIME_CONSTANT;
+		}
+
+		voltage
+	}
+}
+
+/// Take-back-half gain in volts per radian of error built up
+fn take_back_half(clock: &ManualClock) -> TakeBackHalf<ManualClock> {
+	TakeBackHalf::with_clock(
+		radians_per_second(TARGET),
+		volts(0.01) / Angle::new::<radian>(1.0),
+		radians_per_second(5.0),
+		clock.clone(),
+	)
+}
+
+#[test]
+fn take_back_half_converges_on_the_holding_voltage() {
+	let mut flywheel: Flywheel = Flywheel::new();
+	let mut controller: TakeBackHalf<ManualClock> = take_back_half(&flywheel.clock);
+
+	let voltage: f64 = flywheel.run(&mut controller, 3000);
+
+	assert!(
+		(flywheel.velocity - TARGET).abs() < 2.0,
+		"settled at {} rad/s",
+		flywheel.velocity
+	);
+	assert!(
+		(voltage - TARGET / SPEED_PER_VOLT).abs() < 0.1,
+		"holding with {voltage} V"
+	);
+	assert!(controller.is_at_speed(radians_per_second(flywheel.velocity)));
+}
+
+#[test]
+fn take_back_half_halves_back_towards_the_holding_estimate_on_crossing_the_target() {
+	let clock: ManualClock = ManualClock::new();
+	let mut controller: TakeBackHalf<ManualClock> = take_back_half(&clock).with_holding_estimate(volts(7.0));
+
+	// Short of the target by 100 rad/s for a second builds up a volt
+	let mut voltage: ElectricPotential = volts(0.0);
+	for _ in 0..100 {
+		clock.advance(Time::new::<millisecond>(CYCLE_MILLISECONDS));
+		voltage = controller.cycle(radians_per_second(TARGET - 100.0));
+	}
+	assert!((voltage.get::<volt>() - 1.0).abs() < 1e-9);
+
+	// Overshooting halves the voltage back towards the estimate, after taking in this cycle's error
+	clock.advance(Time::new::<millisecond>(CYCLE_MILLISECONDS));
+	let voltage: f64 = controller.cycle(radians_per_second(TARGET + 100.0)).get::<volt>();
+	assert!((voltage - (1.0 - 0.01 + 7.0) / 2.0).abs() < 1e-9);
+}
+
+#[test]
+fn take_back_half_never_drives_backwards() {
+	let clock: ManualClock = ManualClock::new();
+	let mut controller: TakeBackHalf<ManualClock> = take_back_half(&clock);
+
+	clock.advance(Time::new::<second>(1.0));
+	assert_eq!(controller.cycle(radians_per_second(TARGET * 2.0)), volts(0.0));
+}
+
+#[test]
+fn bang_bang_switches_past_the_hysteresis() {
+	let mut controller: BangBang = BangBang::new(radians_per_second(TARGET), radians_per_second(5.0))
+		.with_hysteresis(radians_per_second(-3.0))
+		.with_voltages(volts(12.0), volts(6.0));
+
+	assert_eq!(controller.cycle(radians_per_second(0.0)), volts(12.0));
+	// Within the hysteresis either side of the target the voltage is left as it was
+	assert_eq!(controller.cycle(radians_per_second(TARGET + 2.0)), volts(12.0));
+	assert_eq!(controller.cycle(radians_per_second(TARGET + 4.0)), volts(6.0));
+	assert_eq!(controller.cycle(radians_per_second(TARGET - 2.0)), volts(6.0));
+	assert_eq!(controller.cycle(radians_per_second(TARGET - 4.0)), volts(12.0));
+}
+
+#[test]
+fn bang_bang_holds_the_flywheel_near_the_target() {
+	let mut flywheel: Flywheel = Flywheel::new();
+	let mut controller: BangBang = BangBang::new(radians_per_second(TARGET), radians_per_second(10.0));
+
+	flywheel.run(&mut controller, 300);
+
+	for _ in 0..100 {
+		flywheel.run(&mut controller, 1);
+		assert!(
+			controller.is_at_speed(radians_per_second(flywheel.velocity)),
+			"strayed to {} rad/s",
+			flywheel.velocity
+		);
+	}
+}
+
+/// Feeds the metrics a velocity for a number of cycles, returning the recovery the last cycle finished, if any
+fn watch(
+	metrics: &mut RecoveryMetrics<ManualClock>, clock: &ManualClock, velocity: f64, cycles: usize,
+) -> Option<Recovery> {
+	let mut recovery: Option<Recovery> = None;
+
+	for _ in 0..cycles {
+		clock.advance(Time::new::<millisecond>(CYCLE_MILLISECONDS));
+		recovery = metrics.update(radians_per_second(TARGET), radians_per_second(velocity));
+	}
+
+	recovery
+}
+
+#[test]
+fn measures_the_dip_and_recovery_time_of_a_shot() {
+	let clock: ManualClock = ManualClock::new();
+	let mut metrics: RecoveryMetrics<ManualClock> = RecoveryMetrics::with_clock(radians_per_second(5.0), clock.clone())
+		.with_shot_threshold(radians_per_second(20.0));
+
+	// Spinning up is not a shot
+	assert!(watch(&mut metrics, &clock, 100.0, 10).is_none());
+	assert!(watch(&mut metrics, &clock, TARGET, 10).is_none());
+
+	assert!(watch(&mut metrics, &clock, TARGET - 60.0, 10).is_none());
+	assert!(watch(&mut metrics, &clock, TARGET - 30.0, 10).is_none());
+
+	let recovery: Recovery = watch(&mut metrics, &clock, TARGET, 1).unwrap();
+	assert_eq!(recovery.dip, radians_per_second(60.0));
+	assert!((recovery.recovery_time.get::<millisecond>() - 20.0 * CYCLE_MILLISECONDS).abs() < 1e-6);
+
+	assert_eq!(metrics.recoveries().len(), 1);
+}
+
+#[test]
+fn ignores_ripple_shallower_than_a_shot() {
+	let clock: ManualClock = ManualClock::new();
+	let mut metrics: RecoveryMetrics<ManualClock> = RecoveryMetrics::with_clock(radians_per_second(5.0), clock.clone())
+		.with_shot_threshold(radians_per_second(20.0));
+
+	watch(&mut metrics, &clock, TARGET, 10);
+
+	for _ in 0..5 {
+		assert!(watch(&mut metrics, &clock, TARGET - 10.0, 3).is_none());
+		assert!(watch(&mut metrics, &clock, TARGET, 3).is_none());
+	}
+
+	assert!(metrics.recoveries().is_empty());
+	assert!(metrics.mean_recovery_time().is_none());
+	assert!(metrics.worst_dip().is_none());
+}
+
+#[test]
+fn summarises_every_shot_until_cleared() {
+	let clock: ManualClock = ManualClock::new();
+	let mut metrics: RecoveryMetrics<ManualClock> = RecoveryMetrics::with_clock(radians_per_second(5.0), clock.clone());
+
+	watch(&mut metrics, &clock, TARGET, 10);
+
+	watch(&mut metrics, &clock, TARGET - 40.0, 10);
+	watch(&mut metrics, &clock, TARGET, 10);
+
+	watch(&mut metrics, &clock, TARGET - 80.0, 30);
+	watch(&mut metrics, &clock, TARGET, 10);
+
+	assert_eq!(metrics.recoveries().len(), 2);
+	assert_eq!(metrics.worst_dip(), Some(radians_per_second(80.0)));
+
+	let mean: f64 = metrics.mean_recovery_time().unwrap().get::<millisecond>();
+	assert!(
+		(mean - 20.0 * CYCLE_MILLISECONDS).abs() < 1e-6,
+		"mean recovery of {mean} ms"
+	);
+
+	metrics.clear();
+	assert!(metrics.recoveries().is_empty());
+
+	// After clearing, the flywheel has to be seen at speed again before a dip counts
+	watch(&mut metrics, &clock, TARGET - 40.0, 10);
+	watch(&mut metrics, &clock, TARGET, 10);
+	assert!(metrics.recoveries().is_empty());
+}
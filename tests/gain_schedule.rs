@@ -0,0 +1,148 @@
+//! Checks the gains a schedule gives at, between and beyond its points, and that a scheduled controller looks them up
+//! by the right variable
+
+use uom::si::{
+	angle::{degree, radian},
+	angular_velocity::radian_per_second,
+	f64::{Angle, AngularVelocity, Frequency, FrequencyDrift, Mass, Ratio, Time},
+	frequency::hertz,
+	frequency_drift::hertz_per_second,
+	mass::kilogram,
+	ratio::ratio,
+	time::millisecond,
+};
+use vex_rs_lib::{
+	clock::ManualClock,
+	gain_schedule::{GainSchedule, ScheduledPid},
+	pid::{PidGains, PositionController},
+	Gains,
+};
+
+/// Gains in radians per second per radian, per radian second and per radian per second
+fn gains(proportional: f64, integral: f64, derivative: f64) -> PidGains<Angle, AngularVelocity> {
+	Gains {
+		proportional: Frequency::new::<hertz>(proportional),
+		integral: FrequencyDrift::new::<hertz_per_second>(integral),
+		derivative: Ratio::new::<ratio>(derivative),
+	}
+}
+
+fn degrees(value: f64) -> Angle { Angle::new::<degree>(value) }
+
+/// Stiff gains for short turns, easing off for long ones
+fn turn_schedule() -> GainSchedule<Angle, Angle, AngularVelocity> {
+	GainSchedule::new(degrees(180.0), gains(2.0, 0.0, 0.1))
+		.with_point(degrees(10.0), gains(6.0, 1.0, 0.3))
+		.with_point(degrees(90.0), gains(4.0, 0.5, 0.2))
+}
+
+fn assert_gains(actual: PidGains<Angle, AngularVelocity>, expected: PidGains<Angle, AngularVelocity>) {
+	let near = |actual: f64, expected: f64| (actual - expected).abs() < 1e-9;
+
+	assert!(
+		near(actual.proportional.value, expected.proportional.value)
+			&& near(actual.integral.value, expected.integral.value)
+			&& near(actual.derivative.value, expected.derivative.value),
+		"expected {:?}, got {:?}",
+		expected,
+		actual
+	);
+}
+
+#[test]
+fn gives_the_gains_of_a_point_at_that_point() {
+	let schedule = turn_schedule();
+
+	assert_gains(schedule.gains(degrees(10.0)), gains(6.0, 1.0, 0.3));
+	assert_gains(schedule.gains(degrees(90.0)), gains(4.0, 0.5, 0.2));
+	assert_gains(schedule.gains(degrees(180.0)), gains(2.0, 0.0, 0.1));
+}
+
+#[test]
+fn interpolates_between_points() {
+	let schedule = turn_schedule();
+
+	assert_gains(schedule.gains(degrees(50.0)), gains(5.0, 0.75, 0.25));
+	assert_gains(schedule.gains(degrees(112.5)), gains(3.5, 0.375, 0.175));
+}
+
+#[test]
+fn holds_the_outermost_gains_out_of_range() {
+	let schedule = turn_schedule();
+
+	assert_gains(schedule.gains(degrees(0.0)), gains(6.0, 1.0, 0.3));
+	assert_gains(schedule.gains(degrees(-45.0)), gains(6.0, 1.0, 0.3));
+	assert_gains(schedule.gains(degrees(720.0)), gains(2.0, 0.0, 0.1));
+}
+
+#[test]
+fn replaces_the_gains_of_a_point_given_twice() {
+	let schedule = turn_schedule().with_point(degrees(90.0), gains(3.0, 0.0, 0.0));
+
+	assert_gains(schedule.gains(degrees(90.0)), gains(3.0, 0.0, 0.0));
+	assert_gains(schedule.gains(degrees(50.0)), gains(4.5, 0.5, 0.15));
+}
+
+#[test]
+fn runs_the_same_gains_everywhere_with_one_point() {
+	let schedule: GainSchedule<Angle, Angle, AngularVelocity> = GainSchedule::new(degrees(90.0), gains(1.0, 2.0, 3.0));
+
+	assert_gains(schedule.gains(degrees(0.0)), gains(1.0, 2.0, 3.0));
+	assert_gains(schedule.gains(degrees(360.0)), gains(1.0, 2.0, 3.0));
+}
+
+/// Controller turning to a target, with gains which are overwritten by the schedule every cycle
+fn pid(target: Angle, clock: &ManualClock) -> PositionController<ManualClock> {
+	PositionController::with_clock(target, gains(0.0, 0.0, 0.0), degrees(1.0), clock.clone())
+}
+
+#[test]
+fn schedules_on_the_size_of_the_error() {
+	let clock: ManualClock = ManualClock::new();
+	let mut controller = ScheduledPid::on_error(pid(degrees(0.0), &clock), turn_schedule());
+
+	clock.advance(Time::new::<millisecond>(10.0));
+	controller.cycle(degrees(50.0));
+	assert_gains(controller.pid().gains(), gains(5.0, 0.75, 0.25));
+
+	clock.advance(Time::new::<millisecond>(10.0));
+	controller.cycle(degrees(-90.0));
+	assert_gains(controller.pid().gains(), gains(4.0, 0.5, 0.2));
+}
+
+#[test]
+fn schedules_on_the_target() {
+	let clock: ManualClock = ManualClock::new();
+	let mut controller = ScheduledPid::on_target(pid(degrees(90.0), &clock), turn_schedule());
+
+	clock.advance(Time::new::<millisecond>(10.0));
+	let output: AngularVelocity = controller.cycle(degrees(80.0));
+
+	assert_gains(controller.pid().gains(), gains(4.0, 0.5, 0.2));
+	assert!(output.get::<radian_per_second>() > 0.0);
+
+	controller.set_target(degrees(10.0));
+	clock.advance(Time::new::<millisecond>(10.0));
+	controller.cycle(degrees(80.0));
+	assert_gains(controller.pid().gains(), gains(6.0, 1.0, 0.3));
+}
+
+#[test]
+fn schedules_on_a_variable_it_is_told_about() {
+	let clock: ManualClock = ManualClock::new();
+	let schedule: GainSchedule<Mass, Angle, AngularVelocity> =
+		GainSchedule::new(Mass::new::<kilogram>(0.0), gains(1.0, 0.0, 0.0))
+			.with_point(Mass::new::<kilogram>(2.0), gains(3.0, 0.0, 0.0));
+
+	let mut controller = ScheduledPid::on_variable(pid(degrees(90.0), &clock), schedule, Mass::new::<kilogram>(0.5));
+
+	clock.advance(Time::new::<millisecond>(10.0));
+	let output: f64 = controller.cycle(degrees(0.0)).get::<radian_per_second>();
+	assert_gains(controller.pid().gains(), gains(1.5, 0.0, 0.0));
+	assert!((output - 1.5 * degrees(90.0).get::<radian>()).abs() < 1e-9);
+
+	controller.set_variable(Mass::new::<kilogram>(3.0));
+	clock.advance(Time::new::<millisecond>(10.0));
+	controller.cycle(degrees(0.0));
+	assert_gains(controller.pid().gains(), gains(3.0, 0.0, 0.0));
+}
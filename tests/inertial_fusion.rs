@@ -0,0 +1,76 @@
+//! Checks that odometry fusing an inertial sensor keeps every movement of the wheels when the sensor fails to read
+
+use std::{cell::Cell, rc::Rc};
+
+use uom::si::{
+	angle::radian,
+	f64::{Angle, Length},
+	length::inch,
+};
+use vex_rs_lib::{
+	encoder::MockEncoder,
+	inertial::InertialSensor,
+	odometry::{HeadingFusion, OdometryError, OdometrySystem, TrackingLayout},
+};
+
+const WHEEL_DIAMETER_INCHES: f64 = 2.75;
+const OFFSET_INCHES: f64 = 5.0;
+
+/// Inertial sensor which stays still and can be made to fail
+#[derive(Clone, Default)]
+struct FlakySensor {
+	failing: Rc<Cell<bool>>,
+}
+
+impl InertialSensor for FlakySensor {
+	type Error = ();
+
+	fn get_rotation(&self) -> Result<Angle, Self::Error> {
+		if self.failing.get() {
+			Err(())
+		} else {
+			Ok(Angle::new::<radian>(0.0))
+		}
+	}
+}
+
+/// Rolls both wheels forwards by a distance in inches
+fn roll(left: &MockEncoder, right: &MockEncoder, inches: f64) {
+	let angle: Angle = Angle::new::<radian>(inches / (WHEEL_DIAMETER_INCHES / 2.0));
+	left.rotate(angle);
+	right.rotate(angle);
+}
+
+#[test]
+fn movement_is_kept_through_a_failed_inertial_read() {
+	let left: MockEncoder = MockEncoder::new();
+	let right: MockEncoder = MockEncoder::new();
+	let sensor: FlakySensor = FlakySensor::default();
+
+	let offset: Length = Length::new::<inch>(OFFSET_INCHES);
+	let mut odometry = OdometrySystem::new_with_inertial(
+		TrackingLayout::two_wheel(
+			left.clone(),
+			right.clone(),
+			Length::new::<inch>(WHEEL_DIAMETER_INCHES),
+			offset,
+			offset,
+		),
+		sensor.clone(),
+		HeadingFusion::Replace,
+	)
+	.unwrap();
+
+	roll(&left, &right, 10.0);
+	odometry.cycle().unwrap();
+
+	roll(&left, &right, 6.0);
+	sensor.failing.set(true);
+	assert!(matches!(odometry.cycle(), Err(OdometryError::Inertial(()))));
+
+	sensor.failing.set(false);
+	odometry.cycle().unwrap();
+
+	let y: f64 = odometry.get_position().0.y.get::<inch>();
+	assert!((y - 16.0).abs() < 1e-9, "odometry is at {y} in after driving 16 in");
+}
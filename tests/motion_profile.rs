@@ -0,0 +1,65 @@
+//! Samples trapezoidal and S-curve profiles finely to check that they end where they should and stay within their
+//! limits along the way
+
+use uom::si::{
+	acceleration::meter_per_second_squared,
+	f64::{Acceleration, Jerk, Length, Time, Velocity},
+	jerk::meter_per_second_cubed,
+	length::meter,
+	time::second,
+	velocity::meter_per_second,
+};
+use vex_rs_lib::motion_profile::{Constraints, MotionProfile, Setpoint};
+
+const MAX_VELOCITY: f64 = 1.5;
+const MAX_ACCELERATION: f64 = 3.0;
+const MAX_JERK: f64 = 10.0;
+
+fn constraints(jerk_limited: bool) -> Constraints<Length> {
+	Constraints {
+		max_velocity: Velocity::new::<meter_per_second>(MAX_VELOCITY),
+		max_acceleration: Acceleration::new::<meter_per_second_squared>(MAX_ACCELERATION),
+		max_jerk: jerk_limited.then(|| Jerk::new::<meter_per_second_cubed>(MAX_JERK)),
+	}
+}
+
+/// Samples a profile at evenly spaced times from its start to its end
+fn samples(profile: &MotionProfile<Length>) -> Vec<Setpoint<Length>> {
+	let duration: f64 = profile.duration().get::<second>();
+
+	(0..=1000)
+		.map(|step| profile.sample(Time::new::<second>(duration * step as f64 / 1000.0)))
+		.collect()
+}
+
+#[test]
+fn reaches_the_distance_within_the_limits() {
+	for jerk_limited in [false, true] {
+		// Long enough to cruise, too short to reach the velocity limit, and backwards
+		for distance in [2.0, 0.05, -1.0] {
+			let profile: MotionProfile<Length> =
+				MotionProfile::new(Length::new::<meter>(distance), constraints(jerk_limited));
+
+			for setpoint in samples(&profile) {
+				assert!(setpoint.velocity.get::<meter_per_second>().abs() <= MAX_VELOCITY + 1e-9);
+				assert!(setpoint.acceleration.get::<meter_per_second_squared>().abs() <= MAX_ACCELERATION + 1e-9);
+			}
+
+			let just_before_end: Setpoint<Length> = profile.sample(profile.duration() - Time::new::<second>(1e-9));
+			let error: f64 = just_before_end.position.get::<meter>() - distance;
+			assert!(error.abs() < 1e-6, "ended {error} m from {distance} m");
+			assert!(profile.is_finished(profile.duration()));
+		}
+	}
+}
+
+#[test]
+fn s_curve_changes_acceleration_smoothly() {
+	let profile: MotionProfile<Length> = MotionProfile::new(Length::new::<meter>(2.0), constraints(true));
+	let step: f64 = profile.duration().get::<second>() / 1000.0;
+
+	for pair in samples(&profile).windows(2) {
+		let jerk: f64 = (pair[1].acceleration - pair[0].acceleration).get::<meter_per_second_squared>() / step;
+		assert!(jerk.abs() <= MAX_JERK + 1e-6, "jerk of {jerk} m/s³");
+	}
+}
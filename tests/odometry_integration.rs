@@ -0,0 +1,241 @@
+//! Compares the odometry integration schemes against trajectories integrated finely enough to be taken as the truth
+
+use uom::si::{
+	angle::radian,
+	f64::{Angle, Length},
+	length::{inch, meter},
+};
+use vex_rs_lib::{
+	encoder::MockEncoder,
+	odometry::{Integration, OdometrySystem, TrackingLayout},
+};
+
+const WHEEL_DIAMETER_INCHES: f64 = 2.75;
+const SIDE_OFFSET_INCHES: f64 = 5.0;
+const REAR_OFFSET_INCHES: f64 = 4.0;
+
+/// Robot-relative movement over one cycle, as sideways and forward distances in metres and a clockwise rotation in
+/// radians
+#[derive(Clone, Copy)]
+struct Twist {
+	sideways: f64,
+	forward: f64,
+	rotation: f64,
+}
+
+/// Field position as x and y in metres and a heading in radians
+#[derive(Clone, Copy, Debug)]
+struct Pose {
+	x: f64,
+	y: f64,
+	heading: f64,
+}
+
+impl Pose {
+	fn distance_to(&self, other: &Pose) -> f64 { ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt() }
+}
+
+/// Moves a pose along a constant twist in a great many tiny steps
+fn ground_truth(pose: Pose, twist: Twist) -> Pose {
+	const STEPS: usize = 10_000;
+
+	let mut pose: Pose = pose;
+	let sideways: f64 = twist.sideways / STEPS as f64;
+	let forward: f64 = twist.forward / STEPS as f64;
+	let rotation: f64 = twist.rotation / STEPS as f64;
+
+	for _ in 0..STEPS {
+		let heading: f64 = pose.heading + rotation / 2.0;
+
+		pose.x += forward * heading.sin() + sideways * heading.cos();
+		pose.y += forward * heading.cos() - sideways * heading.sin();
+		pose.heading += rotation;
+	}
+
+	pose
+}
+
+/// Three-wheel odometry fed by encoders which the test turns by hand
+struct Rig {
+	left: MockEncoder,
+	right: MockEncoder,
+	rear: MockEncoder,
+	odometry: OdometrySystem<MockEncoder>,
+}
+
+impl Rig {
+	fn new(integration: Integration) -> Self {
+		let left: MockEncoder = MockEncoder::new();
+		let right: MockEncoder = MockEncoder::new();
+		let rear: MockEncoder = MockEncoder::new();
+
+		let odometry: OdometrySystem<MockEncoder> = OdometrySystem::new(TrackingLayout::three_wheel(
+			left.clone(),
+			right.clone(),
+			rear.clone(),
+			Length::new::<inch>(WHEEL_DIAMETER_INCHES),
+			Length::new::<inch>(SIDE_OFFSET_INCHES),
+			Length::new::<inch>(SIDE_OFFSET_INCHES),
+			Length::new::<inch>(REAR_OFFSET_INCHES),
+		))
+		.unwrap()
+		.with_integration(integration);
+
+		Self {
+			left,
+			right,
+			rear,
+			odometry,
+		}
+	}
+
+	/// Turns each wheel by the distance it rolls during a twist, then runs a cycle
+	fn step(&mut self, twist: Twist) {
+		let radius: f64 = Length::new::<inch>(WHEEL_DIAMETER_INCHES / 2.0).get::<meter>();
+		let side_offset: f64 = Length::new::<inch>(SIDE_OFFSET_INCHES).get::<meter>();
+		let rear_offset: f64 = Length::new::<inch>(REAR_OFFSET_INCHES).get::<meter>();
+
+		let roll = |encoder: &MockEncoder, distance: f64| encoder.rotate(Angle::new::<radian>(distance / radius));
+
+		roll(&self.left, twist.forward + twist.rotation * side_offset);
+		roll(&self.right, twist.forward - twist.rotation * side_offset);
+		roll(&self.rear, twist.sideways - twist.rotation * rear_offset);
+
+		self.odometry.cycle().unwrap();
+	}
+
+	fn pose(&self) -> Pose {
+		let position = self.odometry.get_position();
+
+		Pose {
+			x: position.0.x.get::<meter>(),
+			y: position.0.y.get::<meter>(),
+			heading: position.1.get::<radian>(),
+		}
+	}
+}
+
+/// Drives a rig and the ground truth through the same twists, returning how far apart their final positions are and
+/// how far apart their final headings are
+fn run(integration: Integration, twists: impl IntoIterator<Item = Twist>) -> (f64, f64) {
+	let mut rig: Rig = Rig::new(integration);
+	let mut truth: Pose = Pose {
+		x: 0.0,
+		y: 0.0,
+		heading: 0.0,
+	};
+
+	for twist in twists {
+		rig.step(twist);
+		truth = ground_truth(truth, twist);
+	}
+
+	let pose: Pose = rig.pose();
+	(pose.distance_to(&truth), (pose.heading - truth.heading).abs())
+}
+
+/// A quarter turn while strafing outwards, in a given number of cycles
+fn quarter_turn(cycles: usize) -> impl Iterator<Item = Twist> {
+	let twist: Twist = Twist {
+		sideways: 0.2 / cycles as f64,
+		forward: 1.0 / cycles as f64,
+		rotation: core::f64::consts::FRAC_PI_2 / cycles as f64,
+	};
+
+	(0..cycles).map(move |_| twist)
+}
+
+/// A winding path whose speed and curvature change every cycle
+fn slalom(cycles: usize) -> impl Iterator<Item = Twist> {
+	(0..cycles).map(move |cycle| {
+		let phase: f64 = cycle as f64 / cycles as f64 * core::f64::consts::TAU;
+
+		Twist {
+			sideways: 0.01 * (2.0 * phase).cos(),
+			forward: 0.05 + 0.02 * phase.sin(),
+			rotation: 0.2 * (3.0 * phase).sin(),
+		}
+	})
+}
+
+#[test]
+fn exact_arc_follows_constant_curvature_exactly() {
+	let (distance, heading) = run(Integration::ExactArc, quarter_turn(24));
+
+	assert!(distance < 1e-9, "position error {distance} m");
+	assert!(heading < 1e-9, "heading error {heading} rad");
+}
+
+#[test]
+fn midpoint_error_falls_with_the_square_of_the_step() {
+	let (coarse, _) = run(Integration::Midpoint, quarter_turn(24));
+	let (fine, _) = run(Integration::Midpoint, quarter_turn(48));
+
+	assert!(coarse > 1e-6, "midpoint should not be exact, error {coarse} m");
+
+	let ratio: f64 = coarse / fine;
+	assert!(
+		(3.5..4.5).contains(&ratio),
+		"halving the step divided the error by {ratio}"
+	);
+}
+
+#[test]
+fn runge_kutta_converges_with_more_substeps() {
+	let (one, _) = run(Integration::RungeKutta { substeps: 1 }, quarter_turn(24));
+	let (four, _) = run(Integration::RungeKutta { substeps: 4 }, quarter_turn(24));
+	let (midpoint, _) = run(Integration::Midpoint, quarter_turn(24));
+
+	assert!(
+		one < midpoint / 100.0,
+		"one substep {one} m against midpoint {midpoint} m"
+	);
+	assert!(four < one / 100.0, "four substeps {four} m against one substep {one} m");
+	assert!(four < 1e-9, "four substeps {four} m");
+}
+
+#[test]
+fn every_scheme_tracks_a_varying_path() {
+	// Midpoint is only second order, so it drifts by millimetres over the ten metres driven
+	for (integration, tolerance) in [
+		(Integration::ExactArc, 1e-9),
+		(Integration::Midpoint, 1e-2),
+		(Integration::RungeKutta { substeps: 2 }, 1e-6),
+	] {
+		let (distance, heading) = run(integration, slalom(200));
+
+		assert!(distance < tolerance, "{integration:?} position error {distance} m");
+		assert!(heading < 1e-9, "{integration:?} heading error {heading} rad");
+	}
+}
+
+#[test]
+fn straight_and_nearly_straight_movement_agree() {
+	let straight: Twist = Twist {
+		sideways: 0.1,
+		forward: 1.0,
+		rotation: 0.0,
+	};
+	let nearly_straight: Twist = Twist {
+		rotation: 1e-9,
+		..straight
+	};
+
+	for integration in [
+		Integration::ExactArc,
+		Integration::Midpoint,
+		Integration::RungeKutta { substeps: 4 },
+	] {
+		let mut rig: Rig = Rig::new(integration);
+		rig.step(straight);
+		let pose: Pose = rig.pose();
+
+		assert!(
+			(pose.x - 0.1).abs() < 1e-12 && (pose.y - 1.0).abs() < 1e-12,
+			"{integration:?} moved to {pose:?}"
+		);
+
+		let (distance, _) = run(integration, [nearly_straight]);
+		assert!(distance < 1e-9, "{integration:?} position error {distance} m");
+	}
+}
@@ -0,0 +1,171 @@
+//! Checks the filtered velocity and acceleration the odometry differentiates from its position, timed by a manual
+//! clock
+
+use uom::si::{
+	acceleration::meter_per_second_squared,
+	angle::{degree, radian},
+	angular_velocity::radian_per_second,
+	f64::{Angle, Length, Time},
+	length::{inch, meter},
+	time::millisecond,
+	velocity::meter_per_second,
+};
+use vex_rs_lib::{
+	clock::ManualClock,
+	coordinates::Position,
+	encoder::MockEncoder,
+	inertial::NoInertialSensor,
+	odometry::{OdometrySystem, PoseVelocity, TrackingLayout},
+};
+
+const CYCLE_MILLISECONDS: f64 = 10.0;
+const WHEEL_DIAMETER_INCHES: f64 = 2.0;
+const OFFSET_INCHES: f64 = 5.0;
+
+/// Two-wheel odometry whose wheels and clock the test moves by hand
+struct Rig {
+	left: MockEncoder,
+	right: MockEncoder,
+	clock: ManualClock,
+	odometry: OdometrySystem<MockEncoder, NoInertialSensor, ManualClock>,
+}
+
+impl Rig {
+	fn new() -> Self {
+		let left: MockEncoder = MockEncoder::new();
+		let right: MockEncoder = MockEncoder::new();
+		let clock: ManualClock = ManualClock::new();
+
+		let offset: Length = Length::new::<inch>(OFFSET_INCHES);
+		let odometry = OdometrySystem::new(TrackingLayout::two_wheel(
+			left.clone(),
+			right.clone(),
+			Length::new::<inch>(WHEEL_DIAMETER_INCHES),
+			offset,
+			offset,
+		))
+		.unwrap()
+		.with_clock(clock.clone())
+		.with_velocity_filter(Time::new::<millisecond>(30.0));
+
+		Self {
+			left,
+			right,
+			clock,
+			odometry,
+		}
+	}
+
+	/// Rolls the wheels by distances in metres over one cycle
+	fn step(&mut self, left: f64, right: f64) {
+		let radius: f64 = Length::new::<inch>(WHEEL_DIAMETER_INCHES / 2.0).get::<meter>();
+
+		self.left.rotate(Angle::new::<radian>(left / radius));
+		self.right.rotate(Angle::new::<radian>(right / radius));
+		self.clock.advance(Time::new::<millisecond>(CYCLE_MILLISECONDS));
+		self.odometry.cycle().unwrap();
+	}
+
+	/// Drives straight at a speed in metres per second for a number of cycles
+	fn drive(&mut self, speed: f64, cycles: usize) {
+		let distance: f64 = speed * CYCLE_MILLISECONDS / 1000.0;
+
+		for _ in 0..cycles {
+			self.step(distance, distance);
+		}
+	}
+}
+
+#[test]
+fn settles_on_a_steady_speed() {
+	let mut rig: Rig = Rig::new();
+
+	rig.drive(1.5, 100);
+
+	let velocity: PoseVelocity = rig.odometry.get_velocity();
+	assert!((velocity.y.get::<meter_per_second>() - 1.5).abs() < 1e-6);
+	assert!(velocity.x.get::<meter_per_second>().abs() < 1e-9);
+	assert!((velocity.speed().get::<meter_per_second>() - 1.5).abs() < 1e-6);
+
+	let acceleration: f64 = rig
+		.odometry
+		.get_acceleration()
+		.magnitude()
+		.get::<meter_per_second_squared>();
+	assert!(
+		acceleration < 1e-3,
+		"still accelerating at {acceleration} m/s² at a steady speed"
+	);
+}
+
+#[test]
+fn lags_behind_a_change_in_speed() {
+	let mut rig: Rig = Rig::new();
+
+	rig.drive(1.0, 3);
+
+	let velocity: f64 = rig.odometry.get_velocity().y.get::<meter_per_second>();
+	let acceleration: f64 = rig.odometry.get_acceleration().y.get::<meter_per_second_squared>();
+
+	assert!(
+		velocity > 0.3 && velocity < 1.0,
+		"reached {velocity} m/s three cycles in"
+	);
+	assert!(
+		acceleration > 0.0,
+		"accelerating at {acceleration} m/s² while speeding up"
+	);
+}
+
+#[test]
+fn gives_robot_relative_velocity_in_the_direction_the_robot_faces() {
+	let mut rig: Rig = Rig::new();
+	rig.odometry.set_position(Position::new(
+		Length::new::<meter>(1.0),
+		Length::new::<meter>(1.0),
+		Angle::new::<degree>(90.0),
+	));
+
+	rig.drive(1.0, 100);
+
+	let field: PoseVelocity = rig.odometry.get_velocity();
+	let local: PoseVelocity = rig.odometry.get_local_velocity();
+
+	// Facing along the x axis, so driving forwards moves along x on the field
+	assert!((field.x.get::<meter_per_second>() - 1.0).abs() < 1e-6);
+	assert!(field.y.get::<meter_per_second>().abs() < 1e-6);
+	assert!((local.y.get::<meter_per_second>() - 1.0).abs() < 1e-6);
+	assert!(local.x.get::<meter_per_second>().abs() < 1e-6);
+}
+
+#[test]
+fn measures_turning_on_the_spot() {
+	let mut rig: Rig = Rig::new();
+	let distance: f64 = 0.005;
+
+	for _ in 0..100 {
+		rig.step(distance, -distance);
+	}
+
+	let velocity: PoseVelocity = rig.odometry.get_velocity();
+	let track: f64 = Length::new::<inch>(2.0 * OFFSET_INCHES).get::<meter>();
+	let expected: f64 = 2.0 * distance / track / (CYCLE_MILLISECONDS / 1000.0);
+
+	assert!((velocity.angular.get::<radian_per_second>() - expected).abs() < 1e-6);
+	assert!(velocity.speed().get::<meter_per_second>() < 1e-6);
+}
+
+#[test]
+fn ignores_cycles_without_time_passing() {
+	let mut rig: Rig = Rig::new();
+	rig.drive(1.0, 100);
+
+	let before: f64 = rig.odometry.get_velocity().y.get::<meter_per_second>();
+
+	let radius: f64 = Length::new::<inch>(WHEEL_DIAMETER_INCHES / 2.0).get::<meter>();
+	rig.left.rotate(Angle::new::<radian>(0.01 / radius));
+	rig.right.rotate(Angle::new::<radian>(0.01 / radius));
+	rig.odometry.cycle().unwrap();
+
+	assert_eq!(rig.odometry.get_velocity().y.get::<meter_per_second>(), before);
+}
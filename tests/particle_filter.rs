@@ -0,0 +1,113 @@
+//! Checks that the particle filter follows the odometry when it has nothing else to go on, and pulls back onto the
+//! true position from a wrong start once the distance sensors can see the walls
+
+use uom::si::{
+	angle::{degree, radian},
+	f64::{Angle, Length, Ratio},
+	length::inch,
+	ratio::ratio,
+};
+use vex_rs_lib::{
+	coordinates::Position,
+	distance::{MockDistanceSensor, SensorMount},
+	field::FieldMap,
+	localisation::{MotionNoise, ParticleFilter},
+};
+
+fn inches(value: f64) -> Length { Length::new::<inch>(value) }
+
+fn degrees(value: f64) -> Angle { Angle::new::<degree>(value) }
+
+/// Position reached after driving a distance in inches along an arc which turns clockwise by an angle in radians
+fn arc(start: Position, distance: f64, rotation: f64) -> Position {
+	let Position(coordinates, heading) = start;
+	let chord: f64 = if rotation == 0.0 {
+		distance
+	} else {
+		2.0 * distance / rotation * (rotation / 2.0).sin()
+	};
+	let average_heading: f64 = heading.get::<radian>() + rotation / 2.0;
+
+	Position::new(
+		coordinates.x + inches(chord * average_heading.sin()),
+		coordinates.y + inches(chord * average_heading.cos()),
+		heading + Angle::new::<radian>(rotation),
+	)
+}
+
+fn distance_between(a: Position, b: Position) -> f64 { a.0.distance_to(&b.0).get::<inch>() }
+
+#[test]
+fn noiseless_particles_follow_the_odometry_along_an_arc() {
+	let start: Position = Position::new(inches(72.0), inches(36.0), degrees(0.0));
+
+	let mut filter: ParticleFilter<MockDistanceSensor> =
+		ParticleFilter::new(FieldMap::new(), 8, start, inches(0.0), degrees(0.0), 1);
+	filter.motion_noise = MotionNoise {
+		translation: Ratio::new::<ratio>(0.0),
+		rotation: Ratio::new::<ratio>(0.0),
+		drift: Ratio::new::<ratio>(0.0),
+	};
+
+	let mut odometry: Position = start;
+	filter.update(odometry);
+
+	for _ in 0..20 {
+		odometry = arc(odometry, 3.0, 0.15);
+		let estimate: Position = filter.update(odometry);
+
+		let error: f64 = distance_between(estimate, odometry);
+		let heading_error: f64 = (estimate.1 - odometry.1).get::<degree>().abs();
+
+		assert!(error < 1e-9, "estimate is {error} in from the odometry");
+		assert!(
+			heading_error < 1e-9,
+			"estimate heading is {heading_error} degrees from the odometry"
+		);
+	}
+}
+
+#[test]
+fn converges_on_the_true_position_from_a_wrong_start() {
+	let map: FieldMap = FieldMap::new();
+	let mounts: [SensorMount; 4] = [
+		SensorMount::new(inches(0.0), inches(6.0), degrees(0.0)),
+		SensorMount::new(inches(0.0), inches(-6.0), degrees(180.0)),
+		SensorMount::new(inches(-6.0), inches(0.0), degrees(-90.0)),
+		SensorMount::new(inches(6.0), inches(0.0), degrees(90.0)),
+	];
+
+	let mut truth: Position = Position::new(inches(40.0), inches(30.0), degrees(10.0));
+	let offset: (Length, Length) = (inches(5.0), inches(-4.0));
+	let odometry = |truth: Position| Position::new(truth.0.x + offset.0, truth.0.y + offset.1, truth.1);
+
+	let mut filter: ParticleFilter<MockDistanceSensor> =
+		ParticleFilter::new(map.clone(), 400, odometry(truth), inches(8.0), degrees(2.0), 7);
+
+	let sensors: Vec<MockDistanceSensor> = mounts
+		.iter()
+		.map(|mount| {
+			let sensor: MockDistanceSensor = MockDistanceSensor::new();
+			filter.add_sensor(sensor.clone(), *mount);
+			sensor
+		})
+		.collect();
+
+	let start_error: f64 = distance_between(filter.update(odometry(truth)), truth);
+
+	let mut estimate: Position = truth;
+	for _ in 0..60 {
+		truth = arc(truth, 1.0, 0.01);
+
+		for (sensor, mount) in sensors.iter().zip(mounts.iter()) {
+			sensor.set_distance(map.cast(mount.locate(truth)));
+		}
+
+		estimate = filter.update(odometry(truth));
+	}
+
+	let error: f64 = distance_between(estimate, truth);
+
+	assert!(start_error > 4.0, "started only {start_error} in from the truth");
+	assert!(error < 1.5, "estimate is still {error} in from the truth");
+}
@@ -0,0 +1,290 @@
+//! Checks the anti-windup, settling and derivative options of the PID controller, with a manual clock standing in
+//! for the passing of time
+
+use uom::si::{
+	angle::radian,
+	angular_velocity::radian_per_second,
+	f64::{Angle, AngularVelocity, Frequency, FrequencyDrift, Ratio, Time},
+	frequency::hertz,
+	frequency_drift::hertz_per_second,
+	ratio::ratio,
+	time::millisecond,
+};
+use vex_rs_lib::{
+	clock::ManualClock,
+	pid::{AntiWindup, ControllerStatus, DerivativeMode, PidGains, PositionController, Settling, Signal},
+	Gains,
+};
+
+const CYCLE_MILLISECONDS: f64 = 10.0;
+
+/// Gains in radians per second per radian, per radian second and per radian per second
+fn gains(proportional: f64, integral: f64, derivative: f64) -> PidGains<Angle, AngularVelocity> {
+	Gains {
+		proportional: Frequency::new::<hertz>(proportional),
+		integral: FrequencyDrift::new::<hertz_per_second>(integral),
+		derivative: Ratio::new::<ratio>(derivative),
+	}
+}
+
+fn radians(value: f64) -> Angle { Angle::new::<radian>(value) }
+
+/// Time half a cycle short of a number of cycles, so that rounding in the clock cannot move a wait to another cycle
+fn just_under(cycles: f64) -> Time { Time::new::<millisecond>((cycles - 0.5) * CYCLE_MILLISECONDS) }
+
+/// Controller aiming for a target in radians, along with the clock timing it
+fn controller(target: f64, gains: PidGains<Angle, AngularVelocity>) -> (PositionController<ManualClock>, ManualClock) {
+	let clock: ManualClock = ManualClock::new();
+	let controller = PositionController::with_clock(radians(target), gains, radians(0.05), clock.clone());
+
+	(controller, clock)
+}
+
+/// Moves the clock on by a cycle and runs the controller, returning its output in radians per second
+fn cycle(controller: &mut PositionController<ManualClock>, clock: &ManualClock, current: f64) -> f64 {
+	clock.advance(Time::new::<millisecond>(CYCLE_MILLISECONDS));
+	controller.cycle(radians(current)).get::<radian_per_second>()
+}
+
+fn assert_near(actual: f64, expected: f64) {
+	assert!((actual - expected).abs() < 1e-9, "expected {expected}, got {actual}");
+}
+
+#[test]
+fn limits_the_output_either_way() {
+	let (controller, clock) = controller(0.0, gains(10.0, 0.0, 0.0));
+	let mut controller = controller.with_anti_windup(AntiWindup {
+		output_limit: Some(AngularVelocity::new::<radian_per_second>(2.0)),
+		..AntiWindup::default()
+	});
+
+	assert_near(cycle(&mut controller, &clock, -1.0), 2.0);
+	assert_near(cycle(&mut controller, &clock, 1.0), -2.0);
+	assert_near(cycle(&mut controller, &clock, 0.1), -1.0);
+}
+
+#[test]
+fn takes_the_size_of_a_negative_limit_and_ignores_a_limit_which_is_not_a_number() {
+	let (controller, clock) = controller(0.0, gains(10.0, 0.0, 0.0));
+	let mut controller = controller.with_anti_windup(AntiWindup {
+		output_limit: Some(AngularVelocity::new::<radian_per_second>(-2.0)),
+		..AntiWindup::default()
+	});
+
+	assert_near(cycle(&mut controller, &clock, -1.0), 2.0);
+
+	let (controller, clock) = self::controller(0.0, gains(10.0, 1.0, 0.0));
+	let mut controller = controller.with_anti_windup(AntiWindup {
+		integral_limit: Some(Signal::from_base(f64::NAN)),
+		output_limit: Some(AngularVelocity::new::<radian_per_second>(f64::NAN)),
+		..AntiWindup::default()
+	});
+
+	assert_near(cycle(&mut controller, &clock, -1.0), 10.0 + 0.01);
+}
+
+#[test]
+fn limits_the_integral() {
+	let (controller, clock) = controller(1.0, gains(0.0, 2.0, 0.0));
+	// In radian seconds
+	let mut controller = controller.with_anti_windup(AntiWindup {
+		integral_limit: Some(Signal::from_base(0.5)),
+		..AntiWindup::default()
+	});
+
+	let mut output: f64 = 0.0;
+	for _ in 0..200 {
+		output = cycle(&mut controller, &clock, 0.0);
+	}
+
+	assert_near(output, 1.0);
+}
+
+#[test]
+fn holds_the_integral_at_the_value_which_saturates_the_output() {
+	let gains: PidGains<Angle, AngularVelocity> = gains(1.0, 1.0, 0.0);
+	let anti_windup: AntiWindup<Angle, AngularVelocity> = AntiWindup {
+		output_limit: Some(AngularVelocity::new::<radian_per_second>(1.0)),
+		..AntiWindup::default()
+	};
+
+	let (controller, clock) = controller(2.0, gains);
+	let mut controller = controller.with_anti_windup(anti_windup);
+
+	// Stuck well short of the target for ten seconds, which would wind the integral up to twenty radian seconds
+	for _ in 0..1000 {
+		assert_near(cycle(&mut controller, &clock, 0.0), 1.0);
+	}
+
+	// The integral has been pulled back to where the output only just saturates, so an overshoot is answered straight
+	// away
+	let output: f64 = cycle(&mut controller, &clock, 2.5);
+	assert!(output < -0.4, "still pushing on at {output} rad/s after overshooting");
+}
+
+#[test]
+fn clears_the_integral_outside_the_integration_zone() {
+	let (controller, clock) = controller(0.0, gains(0.0, 1.0, 0.0));
+	let mut controller = controller.with_anti_windup(AntiWindup {
+		integration_zone: Some(radians(1.0)),
+		..AntiWindup::default()
+	});
+
+	for _ in 0..100 {
+		cycle(&mut controller, &clock, -0.5);
+	}
+	assert!(cycle(&mut controller, &clock, -0.5) > 0.4);
+
+	assert_near(cycle(&mut controller, &clock, -2.0), 0.0);
+}
+
+#[test]
+fn clears_the_integral_when_the_error_changes_sign() {
+	let (controller, clock) = controller(0.0, gains(0.0, 1.0, 0.0));
+	let mut controller = controller.with_anti_windup(AntiWindup {
+		reset_on_sign_change: true,
+		..AntiWindup::default()
+	});
+
+	for _ in 0..100 {
+		cycle(&mut controller, &clock, -0.5);
+	}
+
+	assert_near(cycle(&mut controller, &clock, 0.5), 0.0);
+	assert_near(cycle(&mut controller, &clock, 0.5), -0.005);
+}
+
+#[test]
+fn settles_once_held_on_target_for_the_dwell_time() {
+	let (controller, clock) = controller(1.0, gains(1.0, 0.0, 0.0));
+	let mut controller = controller.with_settling(Settling {
+		dwell_time: just_under(10.0),
+		..Settling::default()
+	});
+
+	cycle(&mut controller, &clock, 0.5);
+	assert_eq!(controller.status(), ControllerStatus::Running);
+
+	// Passing through the target on an overshoot is not enough
+	cycle(&mut controller, &clock, 1.0);
+	cycle(&mut controller, &clock, 1.5);
+	assert_eq!(controller.status(), ControllerStatus::Running);
+
+	for _ in 0..10 {
+		cycle(&mut controller, &clock, 1.01);
+		assert_eq!(controller.status(), ControllerStatus::Running);
+	}
+
+	cycle(&mut controller, &clock, 1.01);
+	assert_eq!(controller.status(), ControllerStatus::Settled);
+
+	controller.set_target(radians(2.0));
+	assert_eq!(controller.status(), ControllerStatus::Running);
+}
+
+#[test]
+fn waits_for_the_mechanism_to_slow_down_before_settling() {
+	let (controller, clock) = controller(1.0, gains(1.0, 0.0, 0.0));
+	// In radians per second
+	let mut controller = controller.with_settling(Settling {
+		velocity_threshold: Some(Signal::from_base(1.0)),
+		..Settling::default()
+	});
+
+	// Swinging through the target at 4 rad/s, which stays within the completion threshold
+	for _ in 0..20 {
+		cycle(&mut controller, &clock, 0.98);
+		cycle(&mut controller, &clock, 1.02);
+	}
+	assert_eq!(controller.status(), ControllerStatus::Running);
+
+	for _ in 0..12 {
+		cycle(&mut controller, &clock, 1.0);
+	}
+	assert_eq!(controller.status(), ControllerStatus::Settled);
+}
+
+#[test]
+fn times_out_without_settling() {
+	let (controller, clock) = controller(1.0, gains(1.0, 0.0, 0.0));
+	let mut controller = controller.with_settling(Settling {
+		timeout: Some(just_under(100.0)),
+		..Settling::default()
+	});
+
+	for step in 0..99 {
+		cycle(&mut controller, &clock, step as f64 * 0.001);
+		assert_eq!(controller.status(), ControllerStatus::Running);
+	}
+
+	cycle(&mut controller, &clock, 0.1);
+	assert_eq!(controller.status(), ControllerStatus::TimedOut);
+
+	// The status holds even if the mechanism reaches the target afterwards
+	for _ in 0..20 {
+		cycle(&mut controller, &clock, 1.0);
+	}
+	assert_eq!(controller.status(), ControllerStatus::TimedOut);
+}
+
+#[test]
+fn stalls_when_stopped_short_of_the_target() {
+	let (controller, clock) = controller(1.0, gains(1.0, 0.0, 0.0));
+	let mut controller = controller.with_settling(Settling {
+		velocity_threshold: Some(Signal::from_base(0.1)),
+		stall_time: Some(just_under(20.0)),
+		..Settling::default()
+	});
+
+	// Creeping towards the target faster than the velocity threshold is not a stall
+	for step in 0..=50 {
+		cycle(&mut controller, &clock, step as f64 * 0.01);
+	}
+	assert_eq!(controller.status(), ControllerStatus::Running);
+
+	for _ in 0..20 {
+		cycle(&mut controller, &clock, 0.5);
+		assert_eq!(controller.status(), ControllerStatus::Running);
+	}
+
+	cycle(&mut controller, &clock, 0.5);
+	assert_eq!(controller.status(), ControllerStatus::Stalled);
+}
+
+#[test]
+fn kicks_on_a_new_target_when_differentiating_the_error() {
+	let (mut controller, clock) = controller(0.0, gains(0.0, 0.0, 1.0));
+
+	assert_near(cycle(&mut controller, &clock, 0.0), 0.0);
+
+	controller.set_target(radians(1.0));
+	assert_near(cycle(&mut controller, &clock, 0.0), 100.0);
+}
+
+#[test]
+fn ignores_a_new_target_when_differentiating_the_measurement() {
+	let (controller, clock) = controller(0.0, gains(0.0, 0.0, 1.0));
+	let mut controller = controller.with_derivative_mode(DerivativeMode::Measurement);
+
+	assert_near(cycle(&mut controller, &clock, 0.0), 0.0);
+
+	controller.set_target(radians(1.0));
+	assert_near(cycle(&mut controller, &clock, 0.0), 0.0);
+
+	// Moving towards the target at 1 rad/s damps the output just as it would in error mode
+	assert_near(cycle(&mut controller, &clock, 0.01), -1.0);
+}
+
+#[test]
+fn smooths_the_derivative() {
+	let (controller, clock) = controller(0.0, gains(0.0, 0.0, 1.0));
+	let mut controller = controller
+		.with_derivative_mode(DerivativeMode::Measurement)
+		.with_derivative_filter(Time::new::<millisecond>(90.0));
+
+	cycle(&mut controller, &clock, 0.0);
+
+	// A tenth of the way towards the new rate each cycle, with a time constant nine cycles long
+	assert_near(cycle(&mut controller, &clock, 0.01), -0.1);
+	assert_near(cycle(&mut controller, &clock, 0.02), -0.19);
+}
@@ -0,0 +1,227 @@
+//! Checks the corrections distance sensors make against the field walls, and the readings they throw away
+
+use uom::{
+	si::{
+		angle::{degree, radian},
+		f64::{Angle, Length, Ratio},
+		length::inch,
+		ratio::ratio,
+	},
+	ConstZero,
+};
+use vex_rs_lib::{
+	coordinates::Position,
+	distance::{MockDistanceSensor, SensorMount},
+	encoder::MockEncoder,
+	odometry::{OdometrySystem, PoseCovariance, TrackingLayout, TrackingWheel},
+	relocalisation::{Relocalisation, Relocaliser},
+};
+
+fn inches(value: f64) -> Length { Length::new::<inch>(value) }
+
+fn degrees(value: f64) -> Angle { Angle::new::<degree>(value) }
+
+/// Adds a sensor pointing straight back from the tracking centre, offset to the right by a distance in inches
+fn rear_sensor(relocaliser: &mut Relocaliser<MockDistanceSensor>, x: f64) -> MockDistanceSensor {
+	let sensor: MockDistanceSensor = MockDistanceSensor::new();
+	relocaliser.add_sensor(sensor.clone(), SensorMount::new(inches(x), inches(0.0), degrees(180.0)));
+	sensor
+}
+
+/// Robot 20 inches from the red wall and 30 inches from the left wall, facing the blue wall
+fn position() -> Position { Position::new(inches(30.0), inches(20.0), degrees(0.0)) }
+
+fn assert_near(actual: Length, expected: f64) {
+	let actual: f64 = actual.get::<inch>();
+	assert!(
+		(actual - expected).abs() < 1e-9,
+		"expected {expected} in, got {actual} in"
+	);
+}
+
+#[test]
+fn corrects_the_axis_the_wall_fixes() {
+	let mut relocaliser: Relocaliser<MockDistanceSensor> = Relocaliser::new();
+	rear_sensor(&mut relocaliser, 0.0).set_distance(Some(inches(18.0)));
+
+	let Position(coordinates, heading) = relocaliser.relocalise(position()).position;
+
+	assert_near(coordinates.x, 30.0);
+	assert_near(coordinates.y, 18.0);
+	assert!((heading - degrees(0.0)).get::<degree>().abs() < 1e-9);
+}
+
+#[test]
+fn corrects_both_axes_from_walls_in_a_corner() {
+	let mut relocaliser: Relocaliser<MockDistanceSensor> = Relocaliser::new();
+	rear_sensor(&mut relocaliser, 0.0).set_distance(Some(inches(21.0)));
+
+	let left: MockDistanceSensor = MockDistanceSensor::new();
+	relocaliser.add_sensor(left.clone(), SensorMount::new(inches(0.0), inches(0.0), degrees(-90.0)));
+	left.set_distance(Some(inches(32.0)));
+
+	let Relocalisation {
+		position: Position(coordinates, _),
+		x_readings,
+		y_readings,
+	} = relocaliser.relocalise(position());
+
+	assert_near(coordinates.x, 32.0);
+	assert_near(coordinates.y, 21.0);
+	assert_eq!((x_readings, y_readings), (1, 1));
+}
+
+#[test]
+fn averages_readings_which_fix_the_same_axis() {
+	let mut relocaliser: Relocaliser<MockDistanceSensor> = Relocaliser::new();
+	rear_sensor(&mut relocaliser, -4.0).set_distance(Some(inches(18.0)));
+	rear_sensor(&mut relocaliser, 4.0).set_distance(Some(inches(19.0)));
+
+	let Position(coordinates, _) = relocaliser.relocalise(position()).position;
+
+	assert_near(coordinates.y, 18.5);
+}
+
+#[test]
+fn rejects_corrections_larger_than_the_limit() {
+	let mut relocaliser: Relocaliser<MockDistanceSensor> = Relocaliser::new();
+	rear_sensor(&mut relocaliser, -4.0).set_distance(Some(inches(10.0)));
+	rear_sensor(&mut relocaliser, 4.0).set_distance(Some(inches(19.0)));
+
+	let Relocalisation {
+		position: Position(coordinates, _),
+		y_readings,
+		..
+	} = relocaliser.relocalise(position());
+
+	assert_near(coordinates.y, 19.0);
+	assert_eq!(y_readings, 1);
+}
+
+#[test]
+fn ignores_walls_out_of_range() {
+	let mut relocaliser: Relocaliser<MockDistanceSensor> = Relocaliser::new();
+	relocaliser.max_range = inches(12.0);
+	rear_sensor(&mut relocaliser, 0.0).set_distance(Some(inches(18.0)));
+
+	let Position(coordinates, _) = relocaliser.relocalise(position()).position;
+
+	assert_near(coordinates.y, 20.0);
+}
+
+#[test]
+fn ignores_walls_seen_at_a_glancing_angle() {
+	let mut relocaliser: Relocaliser<MockDistanceSensor> = Relocaliser::new();
+	rear_sensor(&mut relocaliser, 0.0).set_distance(Some(inches(27.0)));
+
+	// Turned 45° away from the wall, so the beam meets it well beyond the 30° limit
+	let Position(coordinates, _) = relocaliser
+		.relocalise(Position::new(inches(30.0), inches(20.0), degrees(45.0)))
+		.position;
+
+	assert_near(coordinates.x, 30.0);
+	assert_near(coordinates.y, 20.0);
+}
+
+#[test]
+fn skips_sensors_which_cannot_be_read() {
+	let mut relocaliser: Relocaliser<MockDistanceSensor> = Relocaliser::new();
+	rear_sensor(&mut relocaliser, 0.0).set_distance(None);
+
+	let Position(coordinates, _) = relocaliser.relocalise(position()).position;
+
+	assert_near(coordinates.x, 30.0);
+	assert_near(coordinates.y, 20.0);
+}
+
+/// Odometry which has driven 20 inches forwards from a known position, on wheels with a given noise as a ratio of
+/// the distance they roll
+fn driven_odometry(noise: f64) -> OdometrySystem<MockEncoder> {
+	let left: MockEncoder = MockEncoder::new();
+	let right: MockEncoder = MockEncoder::new();
+
+	let mut odometry = OdometrySystem::new(TrackingLayout::new(vec![
+		TrackingWheel::parallel(left.clone(), inches(2.0), inches(-5.0)).with_noise(Ratio::new::<ratio>(noise)),
+		TrackingWheel::parallel(right.clone(), inches(2.0), inches(5.0)).with_noise(Ratio::new::<ratio>(noise)),
+	]))
+	.unwrap();
+	odometry.set_position(Position::new(inches(30.0), inches(0.0), degrees(0.0)));
+
+	for _ in 0..20 {
+		left.rotate(Angle::new::<radian>(1.0));
+		right.rotate(Angle::new::<radian>(1.0));
+		odometry.cycle().unwrap();
+	}
+
+	odometry
+}
+
+#[test]
+fn corrects_the_odometry_position() {
+	let mut odometry: OdometrySystem<MockEncoder> = driven_odometry(0.02);
+
+	let mut relocaliser: Relocaliser<MockDistanceSensor> = Relocaliser::new();
+	rear_sensor(&mut relocaliser, 0.0).set_distance(Some(inches(18.0)));
+
+	relocaliser.correct(&mut odometry);
+
+	let Position(coordinates, _) = odometry.get_position();
+	assert_near(coordinates.x, 30.0);
+	assert_near(coordinates.y, 18.0);
+}
+
+#[test]
+fn narrows_the_uncertainty_of_corrected_coordinates_to_that_of_the_readings() {
+	let mut odometry: OdometrySystem<MockEncoder> = driven_odometry(0.2);
+	let before: PoseCovariance = odometry.get_covariance();
+
+	let mut relocaliser: Relocaliser<MockDistanceSensor> = Relocaliser::new();
+	rear_sensor(&mut relocaliser, -4.0).set_distance(Some(inches(19.0)));
+	rear_sensor(&mut relocaliser, 4.0).set_distance(Some(inches(19.5)));
+
+	relocaliser.correct(&mut odometry);
+
+	let after: PoseCovariance = odometry.get_covariance();
+	let expected_deviation: Length = relocaliser.sensor_noise / 2.0_f64.sqrt();
+
+	assert!(
+		before.y_deviation() > expected_deviation,
+		"the odometry was already certain"
+	);
+	assert_near(after.y_deviation(), expected_deviation.get::<inch>());
+	assert!(after.x_deviation() > Length::ZERO, "the x uncertainty was cleared");
+	assert_eq!(
+		after.0[0][0], before.0[0][0],
+		"the x uncertainty changed without an x reading"
+	);
+	assert_eq!(after.0[2][2], before.0[2][2], "the heading uncertainty changed");
+}
+
+#[test]
+fn keeps_the_uncertainty_of_odometry_more_certain_than_the_readings() {
+	let mut odometry: OdometrySystem<MockEncoder> = driven_odometry(0.02);
+	let before: PoseCovariance = odometry.get_covariance();
+
+	let mut relocaliser: Relocaliser<MockDistanceSensor> = Relocaliser::new();
+	rear_sensor(&mut relocaliser, 0.0).set_distance(Some(inches(19.0)));
+
+	relocaliser.correct(&mut odometry);
+
+	let after: PoseCovariance = odometry.get_covariance();
+
+	assert!(before.y_deviation() < relocaliser.sensor_noise);
+	assert_near(after.y_deviation(), before.y_deviation().get::<inch>());
+}
+
+#[test]
+fn leaves_the_odometry_alone_without_readings() {
+	let mut odometry: OdometrySystem<MockEncoder> = driven_odometry(0.02);
+	let before: PoseCovariance = odometry.get_covariance();
+
+	let mut relocaliser: Relocaliser<MockDistanceSensor> = Relocaliser::new();
+	rear_sensor(&mut relocaliser, 0.0).set_distance(None);
+
+	relocaliser.correct(&mut odometry);
+
+	assert_eq!(odometry.get_covariance(), before);
+}
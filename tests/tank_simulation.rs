@@ -0,0 +1,243 @@
+//! Drives a simulated tank drive through the drive train and pure pursuit, checking that odometry fed by the
+//! simulation follows where the simulation says the robot is
+
+use uom::si::{
+	acceleration::inch_per_second_squared,
+	angle::{degree, radian},
+	angular_acceleration::radian_per_second_squared,
+	angular_velocity::radian_per_second,
+	electric_potential::volt,
+	f64::{
+		Acceleration,
+		Angle,
+		AngularAcceleration,
+		AngularVelocity,
+		ElectricPotential,
+		Frequency,
+		FrequencyDrift,
+		Length,
+		Mass,
+		Time,
+		Velocity,
+	},
+	frequency::hertz,
+	frequency_drift::hertz_per_second,
+	length::inch,
+	mass::kilogram,
+	time::millisecond,
+	velocity::inch_per_second,
+};
+use vex_rs_lib::{
+	clock::ManualClock,
+	coordinates::{Coordinates, Position},
+	encoder::{MockEncoder, MotorEncoder},
+	motion_profile::Constraints,
+	motor::MockMotor,
+	odometry::{OdometrySystem, TrackingLayout},
+	pid::{AntiWindup, Settling},
+	pure_pursuit::{PurePursuitCommands, PurePursuitSystem},
+	ratio,
+	sim::{Cartridge, SimulatedTrackingWheel, TankChassis, TankSimulation},
+	tank_drive::TankDrive,
+	Gains,
+};
+
+const CYCLE_MILLISECONDS: f64 = 10.0;
+const WHEEL_DIAMETER_INCHES: f64 = 4.0;
+const TRACK_WIDTH_INCHES: f64 = 12.0;
+const TRACKING_WHEEL_DIAMETER_INCHES: f64 = 2.75;
+const TRACKING_WHEEL_OFFSET_INCHES: f64 = 5.0;
+
+/// Two-motor-a-side drive train driving the motors of a simulation of its chassis
+fn rig() -> (TankDrive<MockMotor, 2>, TankSimulation<2>) {
+	let mut drive: TankDrive<MockMotor, 2> = TankDrive {
+		left_motors: [MockMotor::new(), MockMotor::new()],
+		right_motors: [MockMotor::new(), MockMotor::new()],
+		drive_ratio: ratio!(1.0),
+		wheel_diameter: Length::new::<inch>(WHEEL_DIAMETER_INCHES),
+		track_width: Length::new::<inch>(TRACK_WIDTH_INCHES),
+		distance_gains: Gains {
+			proportional: Frequency::new::<hertz>(1.0),
+			integral: FrequencyDrift::new::<hertz_per_second>(0.0),
+			derivative: ratio!(0.0),
+		},
+		turn_gains: Gains {
+			proportional: Frequency::new::<hertz>(1.0),
+			integral: FrequencyDrift::new::<hertz_per_second>(0.0),
+			derivative: ratio!(0.0),
+		},
+		anti_windup: AntiWindup::default(),
+		settling: Settling::default(),
+		drive_constraints: Constraints {
+			max_velocity: Velocity::new::<inch_per_second>(40.0),
+			max_acceleration: Acceleration::new::<inch_per_second_squared>(80.0),
+			max_jerk: None,
+		},
+		turn_constraints: Constraints {
+			max_velocity: Frequency::new::<hertz>(3.0),
+			max_acceleration: FrequencyDrift::new::<hertz_per_second>(6.0),
+			max_jerk: None,
+		},
+		left_velocity_gains: Gains {
+			proportional: ElectricPotential::new::<volt>(0.0) / AngularVelocity::new::<radian_per_second>(1.0),
+			integral: ElectricPotential::new::<volt>(0.0) / Angle::new::<radian>(1.0),
+			derivative: ElectricPotential::new::<volt>(0.0)
+				/ AngularAcceleration::new::<radian_per_second_squared>(1.0),
+		},
+		right_velocity_gains: Gains {
+			proportional: ElectricPotential::new::<volt>(0.0) / AngularVelocity::new::<radian_per_second>(1.0),
+			integral: ElectricPotential::new::<volt>(0.0) / Angle::new::<radian>(1.0),
+			derivative: ElectricPotential::new::<volt>(0.0)
+				/ AngularAcceleration::new::<radian_per_second_squared>(1.0),
+		},
+		position_threshold: Angle::new::<degree>(5.0),
+		velocity_threshold: AngularVelocity::new::<radian_per_second>(0.1),
+	};
+
+	let simulation: TankSimulation<2> = TankSimulation::new(TankChassis::from_drive(
+		&drive,
+		Cartridge::Green,
+		Mass::new::<kilogram>(6.0),
+	));
+
+	drive.left_motors = simulation.left_motors();
+	drive.right_motors = simulation.right_motors();
+
+	(drive, simulation)
+}
+
+/// How far apart two poses are, in inches and degrees
+fn pose_error(odometry: Position, truth: Position) -> (f64, f64) {
+	let Position(odometry_coordinates, odometry_heading) = odometry;
+	let Position(truth_coordinates, truth_heading) = truth;
+
+	(
+		odometry_coordinates.distance_to(&truth_coordinates).get::<inch>(),
+		(odometry_heading - truth_heading).get::<degree>().abs(),
+	)
+}
+
+fn step(simulation: &mut TankSimulation<2>) { simulation.step(Time::new::<millisecond>(CYCLE_MILLISECONDS)); }
+
+#[test]
+fn drive_encoder_odometry_follows_an_arcade_drive() {
+	let (mut drive, mut simulation) = rig();
+
+	let [left_motor, _] = simulation.left_motors();
+	let [right_motor, _] = simulation.right_motors();
+	let mut odometry = OdometrySystem::new(TrackingLayout::drive_encoders(
+		MotorEncoder::new(left_motor),
+		MotorEncoder::new(right_motor),
+		drive.wheel_diameter,
+		drive.drive_ratio,
+		drive.track_width,
+	))
+	.unwrap()
+	.with_clock(simulation.clock());
+
+	// Forwards, then forwards while turning right, then a spin on the spot to the left
+	for (forward, turn, cycles) in [(0.6, 0.0, 100), (0.5, 0.3, 150), (0.0, -0.4, 100)] {
+		for _ in 0..cycles {
+			drive.drive_arcade(ratio!(turn), ratio!(forward)).unwrap();
+			step(&mut simulation);
+			odometry.cycle().unwrap();
+		}
+	}
+
+	let (distance, heading) = pose_error(odometry.get_position(), simulation.get_position());
+	let Position(coordinates, _) = simulation.get_position();
+	let travelled: f64 = coordinates.distance_to(&Coordinates::default()).get::<inch>();
+
+	assert!(travelled > 24.0, "only drove {travelled} in from the start");
+	assert!(distance < 0.1, "odometry is {distance} in from the simulation");
+	assert!(
+		heading < 0.1,
+		"odometry heading is {heading} degrees from the simulation"
+	);
+}
+
+#[test]
+fn tracking_wheel_odometry_follows_a_tank_drive() {
+	let (mut drive, mut simulation) = rig();
+
+	let wheel_diameter: Length = Length::new::<inch>(TRACKING_WHEEL_DIAMETER_INCHES);
+	let offset: Length = Length::new::<inch>(TRACKING_WHEEL_OFFSET_INCHES);
+	let left: MockEncoder = simulation.add_tracking_wheel(SimulatedTrackingWheel::parallel(wheel_diameter, -offset));
+	let right: MockEncoder = simulation.add_tracking_wheel(SimulatedTrackingWheel::parallel(wheel_diameter, offset));
+
+	let mut odometry: OdometrySystem<MockEncoder, _, ManualClock> =
+		OdometrySystem::new(TrackingLayout::two_wheel(left, right, wheel_diameter, offset, offset))
+			.unwrap()
+			.with_clock(simulation.clock());
+
+	for cycle in 0..400 {
+		let steering: f64 = if cycle < 200 { 0.4 } else { -0.2 };
+		drive
+			.drive_tank(ratio!(0.5 + steering), ratio!(0.5 - steering))
+			.unwrap();
+		step(&mut simulation);
+		odometry.cycle().unwrap();
+	}
+
+	let (distance, heading) = pose_error(odometry.get_position(), simulation.get_position());
+
+	assert!(distance < 0.1, "odometry is {distance} in from the simulation");
+	assert!(
+		heading < 0.1,
+		"odometry heading is {heading} degrees from the simulation"
+	);
+}
+
+#[test]
+fn pure_pursuit_drives_the_tank_to_the_end_of_its_path() {
+	let (mut drive, mut simulation) = rig();
+
+	let [left_motor, _] = simulation.left_motors();
+	let [right_motor, _] = simulation.right_motors();
+	let mut odometry = OdometrySystem::new(TrackingLayout::drive_encoders(
+		MotorEncoder::new(left_motor),
+		MotorEncoder::new(right_motor),
+		drive.wheel_diameter,
+		drive.drive_ratio,
+		drive.track_width,
+	))
+	.unwrap()
+	.with_clock(simulation.clock());
+
+	let end: Coordinates = Coordinates::new(Length::new::<inch>(36.0), Length::new::<inch>(72.0));
+
+	let mut pure_pursuit: PurePursuitSystem = PurePursuitSystem::new();
+	pure_pursuit.enabled = true;
+	pure_pursuit.set_sequence(Some(vec![
+		Coordinates::new(Length::new::<inch>(0.0), Length::new::<inch>(36.0)),
+		end,
+	]));
+
+	let mut closest: f64 = f64::INFINITY;
+
+	for _ in 0..1000 {
+		let PurePursuitCommands(distance, turn) = pure_pursuit.cycle(odometry.get_position()).unwrap();
+
+		let forward: f64 = (distance.get::<inch>() / 20.0).clamp(0.0, 0.6) * turn.get::<radian>().cos().max(0.0);
+		let steering: f64 = (turn.get::<radian>() * 0.8).clamp(-0.4, 0.4);
+
+		drive.drive_arcade(ratio!(steering), ratio!(forward)).unwrap();
+		step(&mut simulation);
+		odometry.cycle().unwrap();
+
+		let Position(coordinates, _) = simulation.get_position();
+		closest = closest.min(coordinates.distance_to(&end).get::<inch>());
+	}
+
+	let (distance, heading) = pose_error(odometry.get_position(), simulation.get_position());
+
+	assert!(
+		closest < 20.0,
+		"came no closer than {closest} in to the end of the path"
+	);
+	assert!(distance < 0.5, "odometry is {distance} in from the simulation");
+	assert!(
+		heading < 0.5,
+		"odometry heading is {heading} degrees from the simulation"
+	);
+}
@@ -0,0 +1,156 @@
+//! Runs the velocity controller against a simulated drive train to check that it brings the motors up to speed and
+//! keeps its voltage within the motors' range
+
+use uom::si::{
+	angle::radian,
+	angular_acceleration::radian_per_second_squared,
+	angular_velocity::{radian_per_second, revolution_per_minute},
+	electric_potential::volt,
+	f64::{Angle, AngularAcceleration, AngularVelocity, ElectricPotential, Length, Mass, MomentOfInertia, Ratio, Time},
+	length::inch,
+	mass::kilogram,
+	moment_of_inertia::kilogram_square_meter,
+	ratio::ratio,
+	time::millisecond,
+};
+use vex_rs_lib::{
+	clock::ManualClock,
+	motor::{MockMotor, SmartMotor},
+	pid::{PidGains, VelocityController},
+	sim::{Cartridge, TankChassis, TankSimulation},
+	Gains,
+};
+
+const CYCLE_MILLISECONDS: f64 = 10.0;
+
+/// Gains in volts per radian per second, volts per radian and volts per radian per second squared
+fn gains(proportional: f64, integral: f64, derivative: f64) -> PidGains<AngularVelocity, ElectricPotential> {
+	Gains {
+		proportional: ElectricPotential::new::<volt>(proportional) / AngularVelocity::new::<radian_per_second>(1.0),
+		integral: ElectricPotential::new::<volt>(integral) / Angle::new::<radian>(1.0),
+		derivative: ElectricPotential::new::<volt>(derivative)
+			/ AngularAcceleration::new::<radian_per_second_squared>(1.0),
+	}
+}
+
+fn rpm(value: f64) -> AngularVelocity { AngularVelocity::new::<revolution_per_minute>(value) }
+
+/// Simulated drive train with both sides under velocity control
+struct Rig {
+	simulation: TankSimulation<1>,
+	motors: [MockMotor; 2],
+	controllers: [VelocityController<ManualClock>; 2],
+}
+
+impl Rig {
+	fn new(target: AngularVelocity, gains: PidGains<AngularVelocity, ElectricPotential>) -> Self {
+		let simulation: TankSimulation<1> = TankSimulation::new(TankChassis {
+			cartridge: Cartridge::Green,
+			drive_ratio: Ratio::new::<ratio>(1.0),
+			wheel_diameter: Length::new::<inch>(4.0),
+			track_width: Length::new::<inch>(12.0),
+			mass: Mass::new::<kilogram>(6.0),
+			moment_of_inertia: MomentOfInertia::new::<kilogram_square_meter>(0.15),
+			rolling_resistance: Ratio::new::<ratio>(0.05),
+		});
+
+		let [left] = simulation.left_motors();
+		let [right] = simulation.right_motors();
+
+		let controllers: [VelocityController<ManualClock>; 2] =
+			core::array::from_fn(|_| VelocityController::with_clock(target, gains, rpm(2.0), simulation.clock()));
+
+		Self {
+			simulation,
+			motors: [left, right],
+			controllers,
+		}
+	}
+
+	/// Runs a cycle of each controller, then steps the simulation, returning the voltages applied
+	fn step(&mut self) -> [ElectricPotential; 2] {
+		let mut voltages: [ElectricPotential; 2] = [ElectricPotential::new::<volt>(0.0); 2];
+
+		for ((motor, controller), voltage) in self
+			.motors
+			.iter_mut()
+			.zip(self.controllers.iter_mut())
+			.zip(voltages.iter_mut())
+		{
+			*voltage = controller.cycle(motor.get_actual_velocity().unwrap());
+			motor.move_voltage(*voltage).unwrap();
+		}
+
+		self.simulation.step(Time::new::<millisecond>(CYCLE_MILLISECONDS));
+
+		voltages
+	}
+
+	/// Runs for a length of time, returning the voltages applied on the last cycle
+	fn run(&mut self, milliseconds: f64) -> [ElectricPotential; 2] {
+		let cycles: usize = (milliseconds / CYCLE_MILLISECONDS) as usize;
+
+		(0..cycles).fold([ElectricPotential::new::<volt>(0.0); 2], |_, _| self.step())
+	}
+
+	fn set_target(&mut self, target: AngularVelocity) {
+		for controller in self.controllers.iter_mut() {
+			controller.set_target(target);
+		}
+	}
+
+	fn velocity(&self) -> AngularVelocity { self.motors[0].get_actual_velocity().unwrap() }
+}
+
+#[test]
+fn converges_on_the_target_velocity() {
+	let target: AngularVelocity = rpm(120.0);
+	let mut rig: Rig = Rig::new(target, gains(0.2, 2.0, 0.0));
+
+	rig.run(3000.0);
+
+	let error: f64 = (rig.velocity() - target).get::<revolution_per_minute>();
+	assert!(error.abs() < 1.0, "settled {error} rpm from the target");
+	assert!(rig.controllers[0].is_at_speed(rig.velocity()));
+}
+
+#[test]
+fn holds_the_voltage_steady_once_at_speed() {
+	let mut rig: Rig = Rig::new(rpm(120.0), gains(0.2, 2.0, 0.0));
+
+	let [before, _] = rig.run(3000.0);
+	let [after, _] = rig.run(500.0);
+
+	let drift: f64 = (after - before).get::<volt>();
+	assert!(
+		before.get::<volt>() > 1.0,
+		"holding {} V at speed",
+		before.get::<volt>()
+	);
+	assert!(drift.abs() < 0.01, "voltage drifted by {drift} V");
+}
+
+#[test]
+fn saturates_without_winding_up() {
+	// Far beyond the free speed of a green cartridge, so the controller spends the whole time saturated
+	let mut rig: Rig = Rig::new(rpm(400.0), gains(0.5, 5.0, 0.0));
+
+	for _ in 0..200 {
+		for voltage in rig.step() {
+			assert!(
+				voltage.get::<volt>().abs() <= 12.0,
+				"asked for {} V",
+				voltage.get::<volt>()
+			);
+		}
+	}
+
+	rig.set_target(rpm(120.0));
+	rig.run(1000.0);
+
+	let error: f64 = (rig.velocity() - rpm(120.0)).get::<revolution_per_minute>();
+	assert!(
+		error.abs() < 2.0,
+		"still {error} rpm from the target a second after it was lowered"
+	);
+}
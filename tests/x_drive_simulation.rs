@@ -0,0 +1,166 @@
+//! Strafes and turns a simulated X-drive, checking that three-wheel odometry fed by the simulation follows where the
+//! simulation says the robot is
+
+use uom::si::{
+	angle::degree,
+	f64::{Length, Mass, MomentOfInertia, Time},
+	length::inch,
+	mass::kilogram,
+	moment_of_inertia::kilogram_square_meter,
+	time::millisecond,
+};
+use vex_rs_lib::{
+	clock::ManualClock,
+	coordinates::Position,
+	encoder::MockEncoder,
+	inertial::NoInertialSensor,
+	motor::MockMotor,
+	odometry::{OdometrySystem, TrackingLayout},
+	ratio,
+	sim::{Cartridge, SimulatedTrackingWheel, XDriveChassis, XDriveSimulation},
+	x_drive::XDrive,
+};
+
+const CYCLE_MILLISECONDS: f64 = 10.0;
+const WHEEL_DIAMETER_INCHES: f64 = 3.25;
+const TURN_DIAMETER_INCHES: f64 = 16.0;
+const TRACKING_WHEEL_DIAMETER_INCHES: f64 = 2.75;
+const SIDE_OFFSET_INCHES: f64 = 5.0;
+const REAR_OFFSET_INCHES: f64 = 4.0;
+
+/// X-drive driving the motors of a simulated chassis, followed by three-wheel odometry on the simulation's tracking
+/// wheels
+struct Rig {
+	drive: XDrive<MockMotor>,
+	simulation: XDriveSimulation,
+	odometry: OdometrySystem<MockEncoder, NoInertialSensor, ManualClock>,
+}
+
+impl Rig {
+	fn new() -> Self {
+		let wheel_diameter: Length = Length::new::<inch>(WHEEL_DIAMETER_INCHES);
+		let turn_diameter: Length = Length::new::<inch>(TURN_DIAMETER_INCHES);
+
+		let mut simulation: XDriveSimulation = XDriveSimulation::new(XDriveChassis {
+			cartridge: Cartridge::Green,
+			drive_ratio: ratio!(1.0),
+			wheel_diameter,
+			turn_radius: turn_diameter / 2.0,
+			mass: Mass::new::<kilogram>(6.0),
+			moment_of_inertia: MomentOfInertia::new::<kilogram_square_meter>(0.2),
+			rolling_resistance: ratio!(0.05),
+		});
+
+		let [front_left, back_left, front_right, back_right] = simulation.motors();
+		let drive: XDrive<MockMotor> = XDrive::new(
+			front_left,
+			back_left,
+			front_right,
+			back_right,
+			ratio!(1.0),
+			wheel_diameter,
+			turn_diameter,
+		);
+
+		let tracking_wheel_diameter: Length = Length::new::<inch>(TRACKING_WHEEL_DIAMETER_INCHES);
+		let side_offset: Length = Length::new::<inch>(SIDE_OFFSET_INCHES);
+		let rear_offset: Length = Length::new::<inch>(REAR_OFFSET_INCHES);
+
+		let left: MockEncoder =
+			simulation.add_tracking_wheel(SimulatedTrackingWheel::parallel(tracking_wheel_diameter, -side_offset));
+		let right: MockEncoder =
+			simulation.add_tracking_wheel(SimulatedTrackingWheel::parallel(tracking_wheel_diameter, side_offset));
+		let rear: MockEncoder = simulation.add_tracking_wheel(SimulatedTrackingWheel::perpendicular(
+			tracking_wheel_diameter,
+			-rear_offset,
+		));
+
+		let odometry = OdometrySystem::new(TrackingLayout::three_wheel(
+			left,
+			right,
+			rear,
+			tracking_wheel_diameter,
+			side_offset,
+			side_offset,
+			rear_offset,
+		))
+		.unwrap()
+		.with_clock(simulation.clock());
+
+		Self {
+			drive,
+			simulation,
+			odometry,
+		}
+	}
+
+	/// Drives with the same strafe, forward and turn inputs for a number of cycles
+	fn drive(&mut self, x: f64, y: f64, rotation: f64, cycles: usize) {
+		for _ in 0..cycles {
+			self.drive.drive(ratio!(x), ratio!(y), ratio!(rotation)).unwrap();
+			self.simulation.step(Time::new::<millisecond>(CYCLE_MILLISECONDS));
+			self.odometry.cycle().unwrap();
+		}
+	}
+
+	/// How far the odometry is from the simulation, in inches and degrees
+	fn error(&self) -> (f64, f64) {
+		let Position(odometry_coordinates, odometry_heading) = self.odometry.get_position();
+		let Position(truth_coordinates, truth_heading) = self.simulation.get_position();
+
+		(
+			odometry_coordinates.distance_to(&truth_coordinates).get::<inch>(),
+			(odometry_heading - truth_heading).get::<degree>().abs(),
+		)
+	}
+}
+
+#[test]
+fn odometry_follows_a_strafe() {
+	let mut rig: Rig = Rig::new();
+
+	rig.drive(0.6, 0.0, 0.0, 150);
+
+	let Position(coordinates, heading) = rig.simulation.get_position();
+	let (distance, heading_error) = rig.error();
+
+	assert!(
+		coordinates.x.get::<inch>() > 12.0,
+		"only strafed {:.1} in",
+		coordinates.x.get::<inch>()
+	);
+	assert!(
+		coordinates.y.get::<inch>().abs() < 1.0 && heading.get::<degree>().abs() < 1.0,
+		"strafe drifted to {:.2} in forwards and {:.2} degrees",
+		coordinates.y.get::<inch>(),
+		heading.get::<degree>()
+	);
+	assert!(distance < 0.1, "odometry is {distance} in from the simulation");
+	assert!(
+		heading_error < 0.1,
+		"odometry heading is {heading_error} degrees from the simulation"
+	);
+}
+
+#[test]
+fn odometry_follows_a_strafe_while_turning() {
+	let mut rig: Rig = Rig::new();
+
+	rig.drive(0.0, 0.5, 0.0, 50);
+	rig.drive(-0.4, 0.3, 0.3, 200);
+	rig.drive(0.5, 0.0, -0.2, 100);
+
+	let Position(_, heading) = rig.simulation.get_position();
+	let (distance, heading_error) = rig.error();
+
+	assert!(
+		heading.get::<degree>().abs() > 30.0,
+		"only turned {:.1} degrees",
+		heading.get::<degree>()
+	);
+	assert!(distance < 0.2, "odometry is {distance} in from the simulation");
+	assert!(
+		heading_error < 0.1,
+		"odometry heading is {heading_error} degrees from the simulation"
+	);
+}